@@ -4,8 +4,9 @@ use retro_blit::{
     window::{RetroBlitContext, WindowMode},
     rendering::deformed_rendering::Vertex,
     rendering::tessellation::PathTessellator,
-    window::KeyCode
+    window::{GamepadAxis, GamepadButton, KeyCode}
 };
+use retro_blit::input::{ActionMap, AxisSource, InputSource};
 use retro_blit::rendering::BlittableSurface;
 use retro_blit::rendering::fonts::tri_spaced::Font;
 use crate::components::{Asteroid, Position, SpatialHandle, Velocity};
@@ -35,6 +36,7 @@ pub struct DemoGame {
     pub palette: Vec<[u8; 3]>,
     pub star_sky_sprite: BlittableSurface,
     pub font: Font,
+    pub actions: ActionMap,
     pub flicker_dt_accumulated: f32,
     pub music_handle: Option<usize>,
     pub mute_sounds: bool,
@@ -51,31 +53,31 @@ impl retro_blit::window::ContextHandler for DemoGame {
             &mut self.player_vertices,
             &mut self.player_indices,
             &PLAYER_POINTS
-        );
+        ).unwrap();
 
         PathTessellator::new().tessellate_polyline_fill(
             &mut self.player_scrap_vertices,
             &mut self.player_scrap_indices,
             &PLAYER_SCRAP_POINTS
-        );
+        ).unwrap();
 
         PathTessellator::new().tessellate_polyline_fill(
             &mut self.square_asteroid_vertices,
             &mut self.square_asteroid_indices,
             &SQUARE_ASTEROID_POINTS
-        );
+        ).unwrap();
 
         PathTessellator::new().tessellate_polyline_fill(
             &mut self.round_asteroid_vertices,
             &mut self.round_asteroid_indices,
             &ROUND_ASTEROID_POINTS
-        );
+        ).unwrap();
 
         PathTessellator::new().tessellate_polyline_fill(
             &mut self.rocky_asteroid_vertices,
             &mut self.rocky_asteroid_indices,
             &ROCKY_ASTEROID_POINTS
-        );
+        ).unwrap();
 
         for (idx, &palette_color) in self.palette.iter().enumerate() {
             ctx.set_palette(idx as u8, palette_color);
@@ -85,6 +87,7 @@ impl retro_blit::window::ContextHandler for DemoGame {
     }
 
     fn update(&mut self, ctx: &mut RetroBlitContext, dt: f32) {
+        self.actions.update(ctx);
         self.update_star_sky(ctx, dt);
         self.update_bullet_collisions(ctx);
         self.update_player_collisions(ctx);
@@ -93,10 +96,10 @@ impl retro_blit::window::ContextHandler for DemoGame {
         self.update_life_spans(dt);
         self.update_fire_cool_downs(dt);
         self.update_revive_cool_down(dt);
-        self.update_player_controls(ctx, dt);
-        self.update_player_fire(ctx);
+        self.update_player_controls(dt);
+        self.update_player_fire();
 
-        if (self.game_lost() || self.game_won()) && ctx.is_key_pressed(KeyCode::Enter) {
+        if (self.game_lost() || self.game_won()) && self.actions.is_just_pressed("restart") {
             self.start_new_game();
         }
 
@@ -138,6 +141,32 @@ impl DemoGame {
             palette,
             star_sky_sprite,
             font,
+            actions: ActionMap::new()
+                .with_axis("turn", &[
+                    AxisSource::Digital {
+                        negative: InputSource::Key(KeyCode::A),
+                        positive: InputSource::Key(KeyCode::D)
+                    },
+                    AxisSource::Analog(GamepadAxis::LeftStickX)
+                ])
+                .with_axis("throttle", &[
+                    AxisSource::Digital {
+                        negative: InputSource::Key(KeyCode::S),
+                        positive: InputSource::Key(KeyCode::W)
+                    },
+                    AxisSource::Digital {
+                        negative: InputSource::GamepadButton(GamepadButton::DPadDown),
+                        positive: InputSource::GamepadButton(GamepadButton::DPadUp)
+                    }
+                ])
+                .with_action("fire", &[
+                    InputSource::Key(KeyCode::Space),
+                    InputSource::GamepadButton(GamepadButton::South)
+                ])
+                .with_action("restart", &[
+                    InputSource::Key(KeyCode::Enter),
+                    InputSource::GamepadButton(GamepadButton::Start)
+                ]),
             flicker_dt_accumulated: 0.0,
             music_handle: None,
             mute_sounds: false,
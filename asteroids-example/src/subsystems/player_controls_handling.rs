@@ -1,24 +1,10 @@
-use retro_blit::window::{KeyCode, RetroBlitContext};
 use crate::{components::*, constants::*, DemoGame};
 
 impl DemoGame {
-    pub fn update_player_controls(&mut self, ctx: &mut RetroBlitContext, dt: f32) {
+    pub fn update_player_controls(&mut self, dt: f32) {
         if let Some(player_entity) = self.player_entity {
-            let mut angle_change = 0.0;
-            if ctx.is_key_pressed(KeyCode::A) {
-                angle_change -= dt * PLAYER_ANGULAR_SPEED_DEGREES.to_radians();
-            }
-            if ctx.is_key_pressed(KeyCode::D) {
-                angle_change += dt * PLAYER_ANGULAR_SPEED_DEGREES.to_radians();
-            }
-
-            let mut velocity_change = 0.0;
-            if ctx.is_key_pressed(KeyCode::W) {
-                velocity_change += dt * PLAYER_THROTTLE;
-            }
-            if ctx.is_key_pressed(KeyCode::S) {
-                velocity_change -= dt * PLAYER_THROTTLE;
-            }
+            let angle_change = self.actions.get_axis("turn") * dt * PLAYER_ANGULAR_SPEED_DEGREES.to_radians();
+            let velocity_change = self.actions.get_axis("throttle") * dt * PLAYER_THROTTLE;
 
             if let Ok((_, rotation, velocity)) = self.ecs_world
                 .query_one_mut::<(&Player, &mut Rotation, &mut Velocity)>(player_entity) {
@@ -35,8 +21,8 @@ impl DemoGame {
         }
     }
 
-    pub fn update_player_fire(&mut self, ctx: &mut RetroBlitContext) {
-        if !ctx.is_key_pressed(KeyCode::Space) {
+    pub fn update_player_fire(&mut self) {
+        if !self.actions.is_pressed("fire") {
             return;
         }
 
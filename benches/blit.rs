@@ -0,0 +1,67 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use retro_blit::rendering::blittable::{BlitBuilder, Blittable, BufferProvider, BufferProviderMut, Flip, SizedSurface};
+use retro_blit::rendering::BlittableSurface;
+
+const ITERATIONS: usize = 500;
+
+/// goes through blend_function pixel by pixel, the same as blits did before row hooks
+struct PerPixel<'a, F: Fn(&mut u8, &u8)> {
+    wrapped: &'a BlittableSurface,
+    blend: F
+}
+
+impl<F: Fn(&mut u8, &u8)> SizedSurface for PerPixel<'_, F> {
+    fn get_width(&self) -> usize { self.wrapped.get_width() }
+
+    fn get_height(&self) -> usize { self.wrapped.get_height() }
+}
+
+impl<F: Fn(&mut u8, &u8)> BufferProvider<u8> for PerPixel<'_, F> {
+    fn get_buffer(&self) -> &[u8] { self.wrapped.get_buffer() }
+}
+
+impl<F: Fn(&mut u8, &u8)> Blittable<u8> for PerPixel<'_, F> {
+    #[inline(always)]
+    fn blend_function(&self, dst: &mut u8, src: &u8) { (self.blend)(dst, src) }
+}
+
+fn measure(name: &str, width: u16, height: u16, flip: Flip, source: &impl Blittable<u8>) -> Duration {
+    let mut screen = BlittableSurface::new(width, height);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        BlitBuilder::create(&mut screen, black_box(source))
+            .with_flip(flip)
+            .blit();
+    }
+    let elapsed = start.elapsed();
+    black_box(&screen);
+    println!("{:<40} {:>10.1?} ({:.1?} per blit)", name, elapsed, elapsed / ITERATIONS as u32);
+    elapsed
+}
+
+fn main() {
+    let mut lut = [0u8; 256];
+    for (idx, entry) in lut.iter_mut().enumerate() {
+        *entry = (255 - idx) as u8;
+    }
+
+    for &(width, height) in [(320, 200), (960, 600)].iter() {
+        let mut source = BlittableSurface::new(width, height);
+        for (idx, px) in source.get_buffer_mut().iter_mut().enumerate() {
+            *px = (idx * 7 % 256) as u8;
+        }
+        println!("{}x{}", width, height);
+
+        for &(flip_name, flip) in [("", Flip::None), (" flip x", Flip::X)].iter() {
+            let per_pixel_copy = PerPixel { wrapped: &source, blend: |dst: &mut u8, src: &u8| *dst = *src };
+            let per_pixel_lut = PerPixel { wrapped: &source, blend: |dst: &mut u8, src: &u8| *dst = lut[*src as usize] };
+
+            measure(&format!("  opaque per pixel{}", flip_name), width, height, flip, &per_pixel_copy);
+            measure(&format!("  opaque row copy{}", flip_name), width, height, flip, &source);
+            measure(&format!("  lut per pixel{}", flip_name), width, height, flip, &per_pixel_lut);
+            measure(&format!("  lut wrapper{}", flip_name), width, height, flip, &source.with_lut(&lut));
+            measure(&format!("  color key{}", flip_name), width, height, flip, &source.with_color_key(0));
+        }
+    }
+}
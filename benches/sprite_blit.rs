@@ -0,0 +1,102 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use retro_blit::rendering::blittable::{BlitBuilder, Flip};
+use retro_blit::rendering::BlittableSurface;
+use retro_blit::rendering::blittable::{BufferProvider, BufferProviderMut};
+use retro_blit::rendering::compiled_sprite::CompiledSprite;
+
+// CompiledSprite beats ColorKeyWrapper on big sprites with large transparent areas (the ring and
+// the sparse sprite), most of all when flipped. Small sprites and many short runs (the disc with
+// holes) are faster with the wrapper.
+const ITERATIONS: usize = 20000;
+const COLOR_KEY: u8 = 0;
+
+fn make_sprite(size: u16, inner_radius: i32, with_holes: bool) -> BlittableSurface {
+    // a ring, optionally with diagonal holes to get many short spans
+    let mut sprite = BlittableSurface::new(size, size);
+    let half = size as i32 / 2;
+    for (idx, px) in sprite.get_buffer_mut().iter_mut().enumerate() {
+        let (x, y) = ((idx % size as usize) as i32 - half, (idx / size as usize) as i32 - half);
+        let distance = x * x + y * y;
+        let inside = distance < (half - 1) * (half - 1) && distance >= inner_radius * inner_radius;
+        let hole = with_holes && (x + y).rem_euclid(7) == 0;
+        *px = if inside && !hole { 1 + (idx % 200) as u8 } else { COLOR_KEY };
+    }
+    sprite
+}
+
+fn make_sparse_sprite(width: u16, height: u16) -> BlittableSurface {
+    // a frame with a few small blobs, e.g. a big explosion or a selection outline
+    let mut sprite = BlittableSurface::new(width, height);
+    let (w, h) = (width as usize, height as usize);
+    for (idx, px) in sprite.get_buffer_mut().iter_mut().enumerate() {
+        let (x, y) = (idx % w, idx / w);
+        let frame = x < 2 || y < 2 || x >= w - 2 || y >= h - 2;
+        let blob = (x / 8) % 5 == 2 && (y / 8) % 5 == 2 && x % 8 < 4 && y % 8 < 4;
+        *px = if frame || blob { 1 + (idx % 200) as u8 } else { COLOR_KEY };
+    }
+    sprite
+}
+
+fn measure(name: &str, mut blit: impl FnMut(&mut BlittableSurface, i16, i16)) -> Duration {
+    let mut screen = BlittableSurface::new(320, 200);
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        // some of the blits are clipped by the screen borders
+        let x = (i * 37 % 384) as i16 - 32;
+        let y = (i * 23 % 264) as i16 - 32;
+        blit(&mut screen, x, y);
+    }
+    let elapsed = start.elapsed();
+    black_box(&screen);
+    println!("{:<44} {:>10.1?} ({:.1?} per blit)", name, elapsed, elapsed / ITERATIONS as u32);
+    elapsed
+}
+
+fn main() {
+    let sprites = [
+        ("32x32 disc", make_sprite(32, 0, false)),
+        ("32x32 disc with holes", make_sprite(32, 0, true)),
+        ("128x128 thin ring", make_sprite(128, 56, false)),
+        ("256x192 sparse sprite", make_sparse_sprite(256, 192))
+    ];
+    for (sprite_name, sprite) in sprites.iter() {
+        bench_sprite(sprite_name, sprite);
+    }
+}
+
+fn bench_sprite(sprite_name: &str, sprite: &BlittableSurface) {
+    let compiled = CompiledSprite::from_surface(sprite, COLOR_KEY);
+
+    // both paths should produce the same picture
+    for &flip in [Flip::None, Flip::X, Flip::Y, Flip::XY].iter() {
+        let (mut expected, mut actual) = (BlittableSurface::new(320, 200), BlittableSurface::new(320, 200));
+        for &(x, y) in [(-10, -7), (100, 50), (300, 190)].iter() {
+            BlitBuilder::create(&mut expected, &sprite.with_color_key(COLOR_KEY))
+                .with_dest_pos(x, y)
+                .with_flip(flip)
+                .blit();
+            BlitBuilder::create(&mut actual, &compiled)
+                .with_dest_pos(x, y)
+                .with_flip(flip)
+                .blit();
+        }
+        assert!(expected.get_buffer() == actual.get_buffer());
+    }
+
+    for &(flip_name, flip) in [("", Flip::None), (" flip xy", Flip::XY)].iter() {
+        let wrapper = sprite.with_color_key(COLOR_KEY);
+        measure(&format!("{}: color key wrapper{}", sprite_name, flip_name), |screen, x, y| {
+            BlitBuilder::create(screen, black_box(&wrapper))
+                .with_dest_pos(x, y)
+                .with_flip(flip)
+                .blit();
+        });
+        measure(&format!("{}: compiled sprite{}", sprite_name, flip_name), |screen, x, y| {
+            BlitBuilder::create(screen, black_box(&compiled))
+                .with_dest_pos(x, y)
+                .with_flip(flip)
+                .blit();
+        });
+    }
+}
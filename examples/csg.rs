@@ -1,21 +1,15 @@
-use std::cmp::Ordering;
-use glam::{Mat4, vec3, Vec3, vec4, Vec4Swizzles};
+use glam::{Mat4, vec3, Vec3};
 use retro_blit::math_utils::bsp_3d::{CSG};
-use retro_blit::rendering::deformed_rendering::{TriangleRasterizer, Vertex};
+use retro_blit::rendering::mesh3d::{Camera, Mesh3d, MeshRenderer, ShadeRamp, ShadingMode};
 use retro_blit::window::{RetroBlitContext, ContextHandler, WindowMode};
 
 #[derive(Copy, Clone)]
 struct Color(u8);
 
-#[derive(Copy, Clone)]
-struct Vert {
-    pos: Vec3,
-    normal: Vec3,
-    color: Color
-}
-
 struct App {
-    triangles: Vec<[Vert; 3]>
+    mesh: Mesh3d,
+    camera: Camera,
+    rotation: f32
 }
 impl ContextHandler for App {
     fn get_window_title(&self) -> &'static str {
@@ -67,120 +61,27 @@ impl ContextHandler for App {
     fn update(&mut self, ctx: &mut RetroBlitContext, dt: f32) {
         ctx.clear(0);
 
-        let forward = vec3(0.0, 0.0, 1.0);
+        self.rotation += 1.2 * dt;
+        let model = Mat4::from_axis_angle(vec3(1.0, 1.0, 0.0).normalize_or_zero(), self.rotation);
 
-        let matrix = Mat4::from_axis_angle(vec3(1.0, 1.0, 0.0).normalize_or_zero(), 1.2 * dt);
-        for triangle in self.triangles.iter_mut() {
-            for vert in triangle.iter_mut() {
-                let n = matrix * vec4(vert.normal.x, vert.normal.y, vert.normal.z, 0.0);
-                let p = matrix * vec4(vert.pos.x, vert.pos.y, vert.pos.z, 1.0);
-                vert.normal = n.xyz();
-                vert.pos = p.xyz();
-            }
-        }
-
-        self.triangles.sort_by(|lhs, rhs| {
-            let lhs_center_z = lhs.into_iter()
-                .map(|it| if forward.dot(it.normal) > 0.0 { f32::MAX } else { it.pos.z } )
-                .fold(0.0, |acc, next| acc + next) / 3.0;
-            let rhs_center_z = rhs.into_iter()
-                .map(|it| if forward.dot(it.normal) > 0.0 { f32::MAX } else { it.pos.z } )
-                .fold(0.0, |acc, next| acc + next) / 3.0;
-            if lhs_center_z > rhs_center_z {
-                Ordering::Less
-            } else if lhs_center_z < rhs_center_z {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
-            }
-        });
-
-        let light = vec3(0.4, -0.2, 1.0).normalize_or_zero();
-
-        TriangleRasterizer::create(ctx).rasterize_with_color_iter(
-            self.triangles.iter()
-                .filter(|triangle| forward.dot(triangle[0].normal) <= 0.0)
-                .map(|triangle| {
-                    let mut color_id = triangle[0].color.0 * 64;
-                    let att = (-light).dot(triangle[0].normal).max(0.0) * 63.0;
-                    color_id += att as u8;
-                    (
-                        [
-                             Vertex { position: (
-                                 128.0 + triangle[0].pos.x * 40.0,
-                                 128.0 + triangle[0].pos.y * 40.0
-                             ) },
-                             Vertex { position: (
-                                 128.0 + triangle[1].pos.x * 40.0,
-                                 128.0 + triangle[1].pos.y * 40.0
-                             ) },
-                             Vertex { position: (
-                                 128.0 + triangle[2].pos.x * 40.0,
-                                 128.0 + triangle[2].pos.y * 40.0
-                             ) }
-                         ],
-                        color_id
-                    )
-                })
-        );
+        MeshRenderer::create(&self.camera)
+            .with_light_direction(vec3(0.4, -0.2, 1.0))
+            .with_ambient(0.0)
+            .with_shading(ShadingMode::Flat)
+            .draw(ctx, &self.mesh, model);
     }
 }
 
 fn main() {
-    let polygons = CSG::cuboid([0.0; 3], [1.0, 3.0, 1.0], Color(0))
+    let csg = CSG::cuboid([0.0; 3], [1.0, 3.0, 1.0], Color(0))
         .union(&CSG::cuboid([0.0; 3], [3.0, 1.0, 1.0], Color(1)))
         .union(&CSG::cuboid([0.0; 3], [1.0, 1.0, 3.0], Color(2)))
-        .subtract(&CSG::cuboid([0.0; 3], [2.0, 2.0, 2.0], Color(3)))
-        .polygons;
+        .subtract(&CSG::cuboid([0.0; 3], [2.0, 2.0, 2.0], Color(3)));
 
-    let mut triangles = Vec::new();
-    for poly in polygons.iter() {
-        if poly.vertices.len() >= 3 {
-            triangles.push(
-                [
-                    Vert{
-                        pos: poly.vertices[0].pos,
-                        normal: poly.vertices[0].normal,
-                        color: poly.shared
-                    },
-                    Vert{
-                        pos: poly.vertices[1].pos,
-                        normal: poly.vertices[1].normal,
-                        color: poly.shared
-                    },
-                    Vert{
-                        pos: poly.vertices[2].pos,
-                        normal: poly.vertices[2].normal,
-                        color: poly.shared
-                    }
-                ]
-            );
-            if poly.vertices.len() > 3 {
-                // let's triangle fan then
-                for i in 3..poly.vertices.len() {
-                    triangles.push(
-                        [
-                            Vert{
-                                pos: poly.vertices[0].pos,
-                                normal: poly.vertices[0].normal,
-                                color: poly.shared
-                            },
-                            Vert{
-                                pos: poly.vertices[i-1].pos,
-                                normal: poly.vertices[i-1].normal,
-                                color: poly.shared
-                            },
-                            Vert{
-                                pos: poly.vertices[i].pos,
-                                normal: poly.vertices[i].normal,
-                                color: poly.shared
-                            }
-                        ]
-                    );
-                }
-            }
-        }
-    }
+    let mesh = Mesh3d::from_csg(&csg, |color| ShadeRamp::new(color.0 * 64, 64));
+    let camera = Camera::new(1.0, 1.0, 0.1, 100.0)
+        .with_position(vec3(0.0, 0.0, -7.0))
+        .with_target(Vec3::ZERO);
 
-    retro_blit::window::start(App{ triangles })
-}
\ No newline at end of file
+    retro_blit::window::start(App{ mesh, camera, rotation: 0.0 })
+}
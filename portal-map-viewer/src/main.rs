@@ -6,7 +6,10 @@ use notify::{RecursiveMode, Watcher, RecommendedWatcher, DebouncedEvent};
 use retro_blit::rendering::bresenham::{BresenhamCircleDrawer, LineRasterizer};
 use retro_blit::rendering::fonts::font_align::{HorizontalAlignment, VerticalAlignment};
 use retro_blit::rendering::fonts::tri_spaced::{Font, TextDrawer};
-use retro_blit::window::{RetroBlitContext, WindowMode};
+use retro_blit::rendering::BlittableSurface;
+use retro_blit::rendering::blittable::BufferProviderMut;
+use retro_blit::rendering::portal_rendering::{PortalCamera, PortalMap, PortalRenderer};
+use retro_blit::window::{KeyCode, RetroBlitContext, WindowMode};
 use crate::map_data::MapData;
 
 mod map_data;
@@ -15,6 +18,10 @@ struct App {
     ron_path: PathBuf,
     _watcher: RecommendedWatcher,
     map_data: MapData,
+    portal_map: PortalMap,
+    wall_textures: Vec<BlittableSurface>,
+    camera: PortalCamera,
+    first_person: bool,
     font: Font,
     file_buffer: Vec<u8>,
     rx: mpsc::Receiver<DebouncedEvent>
@@ -36,10 +43,20 @@ impl retro_blit::window::ContextHandler for App {
         ctx.set_palette(255, [100, 100, 255])
     }
 
-    fn update(&mut self, ctx: &mut RetroBlitContext, _dt: f32) {
+    fn update(&mut self, ctx: &mut RetroBlitContext, dt: f32) {
         self.poll_file_changes();
         ctx.clear(0);
 
+        if ctx.is_key_just_pressed(KeyCode::Tab) {
+            self.first_person = !self.first_person;
+        }
+        if self.first_person {
+            self.update_camera(ctx, dt);
+            PortalRenderer::create(&self.portal_map, &self.wall_textures)
+                .draw(ctx, &self.camera);
+            return;
+        }
+
         for region in self.map_data.regions.iter() {
             let color_id = ((region.height * 4.0) as u8).clamp(0, 254);
 
@@ -158,12 +175,44 @@ impl retro_blit::window::ContextHandler for App {
 }
 
 impl App {
+    fn update_camera(&mut self, ctx: &RetroBlitContext, dt: f32) {
+        const TURN_SPEED: f32 = 2.0;
+        const MOVE_SPEED: f32 = 3.0;
+
+        if ctx.is_key_pressed(KeyCode::A) || ctx.is_key_pressed(KeyCode::Left) {
+            self.camera.angle += TURN_SPEED * dt;
+        }
+        if ctx.is_key_pressed(KeyCode::D) || ctx.is_key_pressed(KeyCode::Right) {
+            self.camera.angle -= TURN_SPEED * dt;
+        }
+        let mut step = 0.0;
+        if ctx.is_key_pressed(KeyCode::W) || ctx.is_key_pressed(KeyCode::Up) {
+            step += MOVE_SPEED * dt;
+        }
+        if ctx.is_key_pressed(KeyCode::S) || ctx.is_key_pressed(KeyCode::Down) {
+            step -= MOVE_SPEED * dt;
+        }
+        let (sin, cos) = self.camera.angle.sin_cos();
+        let new_position = (
+            self.camera.position.0 + cos * step,
+            self.camera.position.1 + sin * step
+        );
+        // we don't have collisions, just don't let the camera out of the map
+        if let Some(sector) = self.portal_map.find_sector(new_position.0, new_position.1) {
+            self.camera.position = new_position;
+            self.camera.sector = sector;
+        }
+    }
+
     fn poll_file_changes(&mut self) {
         loop {
             match self.rx.try_recv() {
                 Ok(DebouncedEvent::Write(_)) => {
                     println!("Change detected!");
                     self.map_data = MapData::read_from_path(&self.ron_path, &mut self.file_buffer);
+                    self.portal_map = self.map_data.to_portal_map();
+                    let (x, y) = self.camera.position;
+                    self.camera.sector = self.portal_map.find_sector(x, y).unwrap_or(0);
                 },
                 Ok(_) => (),
                 Err(TryRecvError::Disconnected) => {
@@ -205,10 +254,30 @@ fn main() {
 
     let font = Font::default_font_small().unwrap();
 
+    let portal_map = map_data.to_portal_map();
+    let wall_textures = (0..portal_map.sectors.len())
+        .map(|idx| make_brick_texture(100 + (idx as u8 % 4) * 30))
+        .collect();
+    let camera_position = portal_map.points
+        .first()
+        .map(|&(x, y)| (x + 1.0, y + 1.5))
+        .unwrap_or((0.0, 0.0));
+    let camera = PortalCamera {
+        position: camera_position,
+        eye_height: 1.0,
+        angle: std::f32::consts::FRAC_PI_2,
+        fov: std::f32::consts::FRAC_PI_2,
+        sector: portal_map.find_sector(camera_position.0, camera_position.1).unwrap_or(0)
+    };
+
     let app = App {
         ron_path,
         _watcher: watcher,
         map_data,
+        portal_map,
+        wall_textures,
+        camera,
+        first_person: false,
         font,
         file_buffer,
         rx
@@ -217,3 +286,17 @@ fn main() {
     retro_blit::window::start(app);
 
 }
+
+fn make_brick_texture(color: u8) -> BlittableSurface {
+    let mut texture = BlittableSurface::new(16, 16);
+    for (idx, pix) in texture.get_buffer_mut().iter_mut().enumerate() {
+        let (x, y) = (idx % 16, idx / 16);
+        let shift = if (y / 4) % 2 == 0 { 0 } else { 4 };
+        *pix = if y % 4 == 3 || (x + shift) % 8 == 7 {
+            color / 2
+        } else {
+            color
+        };
+    }
+    texture
+}
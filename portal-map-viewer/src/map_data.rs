@@ -4,6 +4,7 @@ use std::io::Read;
 use std::path::{PathBuf};
 use serde::Deserialize;
 use ron::de::from_reader;
+use retro_blit::rendering::portal_rendering::{PortalMap, Sector};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct MapData {
@@ -19,6 +20,26 @@ impl MapData {
         file.read_to_end(bytes).unwrap();
         from_reader(&bytes[..]).unwrap()
     }
+
+    pub fn to_portal_map(&self) -> PortalMap {
+        PortalMap {
+            points: self.points.iter().map(|it| (it.0, it.1)).collect(),
+            sectors: self.regions
+                .iter()
+                .enumerate()
+                .map(|(idx, region)| Sector {
+                    floor_height: 0.0,
+                    // region heights are authored for the top-down view, scale them down to eye level
+                    ceiling_height: region.height / 4.0,
+                    walls: region.walls.clone(),
+                    portals: region.portals.clone(),
+                    floor_color: 60,
+                    ceiling_color: 30,
+                    wall_texture: idx
+                })
+                .collect()
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Deserialize)]
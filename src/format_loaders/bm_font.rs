@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+use thiserror::Error;
+use crate::rendering::BlittableSurface;
+use crate::rendering::fonts::proportional::{ProportionalFont, ProportionalGlyph};
+
+#[derive(Error, Debug)]
+pub enum BmFontLoadingError {
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("Int parse failed")]
+    FailedToParseInt(#[from] std::num::ParseIntError),
+    #[error("Incorrect signature. 'BMF' expected")]
+    IncorrectSignature,
+    #[error("Unsupported binary format version {0}. Version 3 expected")]
+    UnsupportedVersion(u8),
+    #[error("Expected 'common' block but found nothing")]
+    CommonBlockExpected,
+    #[error("Expected attribute '{0}' but found nothing")]
+    AttributeExpected(&'static str),
+    #[error("Font refers to page {0}, but only {1} page surfaces were given")]
+    PageNotProvided(usize, usize)
+}
+
+/// AngelCode BMFont loader.
+///
+/// Page images are referenced by file names inside of a font descriptor,
+/// so they should be loaded separately (e.g. converted to im256 with the same palette)
+/// and given in the order of their page ids. Color 0 is treated as transparent by default.
+pub struct BmFont;
+
+struct CommonInfo {
+    line_height: usize,
+    base_line: usize,
+    page_count: usize
+}
+
+impl BmFont {
+    /// loads a font descriptor in a text format
+    pub fn load_from_text(mut source: impl Read, pages: Vec<BlittableSurface>) -> Result<ProportionalFont, BmFontLoadingError> {
+        let mut content = String::new();
+        source.read_to_string(&mut content)?;
+
+        let mut common = None;
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+
+        for line in content.lines() {
+            let mut tokens = split_line(line).into_iter();
+            let tag = match tokens.next() {
+                None => continue,
+                Some(tag) => tag
+            };
+            let attributes = tokens
+                .filter_map(|it| it.split_once('='))
+                .collect::<HashMap<_, _>>();
+            let get = |name: &'static str| -> Result<i32, BmFontLoadingError> {
+                let value = attributes
+                    .get(name)
+                    .ok_or(BmFontLoadingError::AttributeExpected(name))?;
+                Ok(i32::from_str(value)?)
+            };
+            match tag {
+                "common" => {
+                    common = Some(CommonInfo {
+                        line_height: get("lineHeight")? as usize,
+                        base_line: get("base")? as usize,
+                        page_count: get("pages")? as usize
+                    });
+                },
+                "char" => {
+                    let chr = match char::from_u32(get("id")? as u32) {
+                        None => continue,
+                        Some(chr) => chr
+                    };
+                    glyphs.insert(chr, ProportionalGlyph {
+                        page: get("page")? as usize,
+                        x: get("x")? as usize,
+                        y: get("y")? as usize,
+                        width: get("width")? as usize,
+                        height: get("height")? as usize,
+                        x_offset: get("xoffset")? as i16,
+                        y_offset: get("yoffset")? as i16,
+                        x_advance: get("xadvance")? as i16
+                    });
+                },
+                "kerning" => {
+                    let first = char::from_u32(get("first")? as u32);
+                    let second = char::from_u32(get("second")? as u32);
+                    if let (Some(first), Some(second)) = (first, second) {
+                        kerning.insert((first, second), get("amount")? as i16);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let common = common.ok_or(BmFontLoadingError::CommonBlockExpected)?;
+        make_font(common, glyphs, kerning, pages)
+    }
+
+    /// loads a font descriptor in a binary format (version 3)
+    pub fn load_from_binary(mut source: impl Read, pages: Vec<BlittableSurface>) -> Result<ProportionalFont, BmFontLoadingError> {
+        let mut signature = [0u8; 4];
+        source.read_exact(&mut signature)?;
+        if &signature[..3] != b"BMF" {
+            return Err(BmFontLoadingError::IncorrectSignature);
+        }
+        if signature[3] != 3 {
+            return Err(BmFontLoadingError::UnsupportedVersion(signature[3]));
+        }
+
+        let mut common = None;
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+
+        let mut block_header = [0u8; 5];
+        let mut block = Vec::new();
+        loop {
+            match source.read_exact(&mut block_header) {
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                other => other?
+            }
+            let block_type = block_header[0];
+            let block_size = u32::from_le_bytes([
+                block_header[1], block_header[2], block_header[3], block_header[4]
+            ]) as usize;
+            block.resize(block_size, 0);
+            source.read_exact(&mut block)?;
+
+            match block_type {
+                2 if block_size >= 10 => {
+                    common = Some(CommonInfo {
+                        line_height: read_u16(&block, 0) as usize,
+                        base_line: read_u16(&block, 2) as usize,
+                        page_count: read_u16(&block, 8) as usize
+                    });
+                },
+                4 => {
+                    for entry in block.chunks_exact(20) {
+                        let chr = match char::from_u32(read_u32(entry, 0)) {
+                            None => continue,
+                            Some(chr) => chr
+                        };
+                        glyphs.insert(chr, ProportionalGlyph {
+                            x: read_u16(entry, 4) as usize,
+                            y: read_u16(entry, 6) as usize,
+                            width: read_u16(entry, 8) as usize,
+                            height: read_u16(entry, 10) as usize,
+                            x_offset: read_u16(entry, 12) as i16,
+                            y_offset: read_u16(entry, 14) as i16,
+                            x_advance: read_u16(entry, 16) as i16,
+                            page: entry[18] as usize
+                        });
+                    }
+                },
+                5 => {
+                    for entry in block.chunks_exact(10) {
+                        let first = char::from_u32(read_u32(entry, 0));
+                        let second = char::from_u32(read_u32(entry, 4));
+                        if let (Some(first), Some(second)) = (first, second) {
+                            kerning.insert((first, second), read_u16(entry, 8) as i16);
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let common = common.ok_or(BmFontLoadingError::CommonBlockExpected)?;
+        make_font(common, glyphs, kerning, pages)
+    }
+}
+
+fn make_font(
+    common: CommonInfo,
+    glyphs: HashMap<char, ProportionalGlyph>,
+    kerning: HashMap<(char, char), i16>,
+    pages: Vec<BlittableSurface>
+) -> Result<ProportionalFont, BmFontLoadingError> {
+    if pages.len() < common.page_count {
+        return Err(BmFontLoadingError::PageNotProvided(common.page_count - 1, pages.len()));
+    }
+    Ok(ProportionalFont::new(common.line_height, common.base_line, glyphs, kerning, pages))
+}
+
+// splits a line by whitespace, but keeps quoted values (e.g. face="Times New Roman") together
+fn split_line(line: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (idx, chr) in line.char_indices() {
+        match (chr, start) {
+            ('"', _) => {
+                quoted = !quoted;
+                if start.is_none() {
+                    start = Some(idx);
+                }
+            },
+            (c, Some(s)) if c.is_whitespace() && !quoted => {
+                result.push(&line[s..idx]);
+                start = None;
+            },
+            (c, None) if !c.is_whitespace() => start = Some(idx),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        result.push(&line[s..]);
+    }
+    result
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
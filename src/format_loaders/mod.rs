@@ -1,2 +1,5 @@
 pub mod im_256;
 pub mod bmp_256;
+pub mod bm_font;
+pub mod psf;
+pub mod obj;
\ No newline at end of file
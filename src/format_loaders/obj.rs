@@ -0,0 +1,131 @@
+use std::io::Read;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ObjLoadingError {
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("Float parse failed")]
+    FailedToParseFloat(#[from] std::num::ParseFloatError),
+    #[error("Int parse failed")]
+    FailedToParseInt(#[from] std::num::ParseIntError),
+    #[error("Expected vertex component but found nothing")]
+    VertexComponentExpected,
+    #[error("Expected uv component but found nothing")]
+    UVComponentExpected,
+    #[error("Expected normal component but found nothing")]
+    NormalComponentExpected,
+    #[error("Face should have at least 3 vertices")]
+    FaceComponentExpected,
+    #[error("Index {0} is out of range")]
+    IndexOutOfRange(i64)
+}
+
+/// zero based indices into ObjModel arrays
+#[derive(Copy, Clone, Debug)]
+pub struct ObjVertexIndex {
+    pub position: usize,
+    pub uv: Option<usize>,
+    pub normal: Option<usize>
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ObjObject {
+    pub name: String,
+    /// polygons as they are in the file, they are not triangulated
+    pub faces: Vec<Vec<ObjVertexIndex>>
+}
+
+/// Wavefront OBJ geometry. Only v, vt, vn, f and o/g entries are read,
+/// everything else (materials, smoothing groups, etc.) is skipped
+#[derive(Clone, Debug, Default)]
+pub struct ObjModel {
+    pub positions: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub normals: Vec<[f32; 3]>,
+    pub objects: Vec<ObjObject>
+}
+
+impl ObjModel {
+    pub fn load_from(mut source: impl Read) -> Result<Self, ObjLoadingError> {
+        let mut content = String::new();
+        source.read_to_string(&mut content)?;
+
+        let mut model = ObjModel::default();
+        let mut current = ObjObject::default();
+
+        for line in content.lines() {
+            let mut splitted = line.split_whitespace();
+            match splitted.next() {
+                Some("v") => {
+                    model.positions.push(read_components(&mut splitted, || ObjLoadingError::VertexComponentExpected)?);
+                },
+                Some("vt") => {
+                    model.uvs.push(read_components(&mut splitted, || ObjLoadingError::UVComponentExpected)?);
+                },
+                Some("vn") => {
+                    model.normals.push(read_components(&mut splitted, || ObjLoadingError::NormalComponentExpected)?);
+                },
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for it in splitted {
+                        face.push(parse_face_index(it, &model)?);
+                    }
+                    if face.len() < 3 {
+                        return Err(ObjLoadingError::FaceComponentExpected);
+                    }
+                    current.faces.push(face);
+                },
+                Some("o") | Some("g") => {
+                    let name = splitted.collect::<Vec<_>>().join(" ");
+                    if current.faces.is_empty() {
+                        current.name = name;
+                    } else {
+                        let finished = std::mem::replace(&mut current, ObjObject { name, faces: Vec::new() });
+                        model.objects.push(finished);
+                    }
+                },
+                _ => {}
+            }
+        }
+        if !current.faces.is_empty() {
+            model.objects.push(current);
+        }
+        Ok(model)
+    }
+}
+
+fn read_components<'a, const N: usize>(
+    splitted: &mut impl Iterator<Item=&'a str>,
+    missing: fn() -> ObjLoadingError
+) -> Result<[f32; N], ObjLoadingError> {
+    let mut result = [0.0f32; N];
+    for comp in result.iter_mut() {
+        *comp = f32::from_str(splitted.next().ok_or_else(missing)?)?;
+    }
+    Ok(result)
+}
+
+// face entries are "v", "v/vt", "v//vn" or "v/vt/vn", indices are 1-based or negative (relative to the end)
+fn parse_face_index(face_id_str: &str, model: &ObjModel) -> Result<ObjVertexIndex, ObjLoadingError> {
+    let mut face_comps = face_id_str.split('/');
+    let resolve = |value: &str, count: usize| -> Result<Option<usize>, ObjLoadingError> {
+        if value.is_empty() {
+            return Ok(None);
+        }
+        let idx = i64::from_str(value)?;
+        let resolved = if idx < 0 { count as i64 + idx } else { idx - 1 };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(ObjLoadingError::IndexOutOfRange(idx));
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = resolve(face_comps.next().unwrap_or(""), model.positions.len())?
+        .ok_or(ObjLoadingError::FaceComponentExpected)?;
+    let uv = resolve(face_comps.next().unwrap_or(""), model.uvs.len())?;
+    let normal = resolve(face_comps.next().unwrap_or(""), model.normals.len())?;
+
+    Ok(ObjVertexIndex { position, uv, normal })
+}
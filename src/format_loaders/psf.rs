@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::io::Read;
+use thiserror::Error;
+use crate::rendering::BlittableSurface;
+use crate::rendering::blittable::BufferProviderMut;
+use crate::rendering::fonts::proportional::{ProportionalFont, ProportionalGlyph};
+
+#[derive(Error, Debug)]
+pub enum PsfLoadingError {
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("Incorrect signature. PSF1 or PSF2 magic expected")]
+    IncorrectSignature,
+    #[error("Bad glyph dimensions {0}x{1}")]
+    BadGlyphDimensions(usize, usize),
+    #[error("Too many glyphs: {0}")]
+    TooManyGlyphs(usize)
+}
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TAB: u8 = 0x02;
+const PSF1_MODE_HAS_SEQ: u8 = 0x04;
+const PSF1_SEPARATOR: u16 = 0xFFFF;
+const PSF1_START_SEQ: u16 = 0xFFFE;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xFF;
+const PSF2_START_SEQ: u8 = 0xFE;
+
+// glyphs are laid out on page surfaces in rows of this size
+const GLYPHS_PER_ROW: usize = 32;
+// limits for values coming from a PSF2 header, way above anything seen in real console fonts
+const MAX_GLYPH_COUNT: usize = 0x10000;
+const MAX_GLYPH_SIZE: usize = 256;
+
+/// Linux console font loader (PSF1 and PSF2).
+///
+/// Glyph pixels are written with foreground_color, background is 0 and treated as transparent.
+/// If a font has no unicode table, glyph indices are mapped to chars directly.
+pub struct Psf;
+
+struct RawPsf {
+    glyph_count: usize,
+    width: usize,
+    height: usize,
+    bytes_per_glyph: usize,
+    bitmaps: Vec<u8>,
+    // one entry per glyph, several chars may share the same glyph
+    unicode_table: Option<Vec<Vec<char>>>
+}
+
+impl Psf {
+    pub fn load_from(mut source: impl Read, foreground_color: u8) -> Result<ProportionalFont, PsfLoadingError> {
+        let mut magic = [0u8; 2];
+        source.read_exact(&mut magic)?;
+        let raw = if magic == PSF1_MAGIC {
+            read_psf1(&mut source)?
+        } else {
+            let mut rest = [0u8; 2];
+            source.read_exact(&mut rest)?;
+            if [magic[0], magic[1], rest[0], rest[1]] != PSF2_MAGIC {
+                return Err(PsfLoadingError::IncorrectSignature);
+            }
+            read_psf2(&mut source)?
+        };
+        Ok(make_font(raw, foreground_color))
+    }
+}
+
+fn read_psf1(source: &mut impl Read) -> Result<RawPsf, PsfLoadingError> {
+    let mut header = [0u8; 2];
+    source.read_exact(&mut header)?;
+    let [mode, char_size] = header;
+    let glyph_count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+    let height = char_size as usize;
+    if height == 0 {
+        return Err(PsfLoadingError::BadGlyphDimensions(8, height));
+    }
+
+    let mut bitmaps = vec![0u8; glyph_count * height];
+    source.read_exact(&mut bitmaps)?;
+
+    let unicode_table = if mode & (PSF1_MODE_HAS_TAB | PSF1_MODE_HAS_SEQ) != 0 {
+        let mut bytes = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        let mut table = vec![Vec::new(); glyph_count];
+        let mut glyph_idx = 0;
+        let mut in_sequence = false;
+        for pair in bytes.chunks_exact(2) {
+            if glyph_idx >= glyph_count {
+                break;
+            }
+            match u16::from_le_bytes([pair[0], pair[1]]) {
+                PSF1_SEPARATOR => {
+                    glyph_idx += 1;
+                    in_sequence = false;
+                },
+                PSF1_START_SEQ => in_sequence = true,
+                code if !in_sequence => {
+                    if let Some(chr) = char::from_u32(code as u32) {
+                        table[glyph_idx].push(chr);
+                    }
+                },
+                _ => {}
+            }
+        }
+        Some(table)
+    } else {
+        None
+    };
+
+    Ok(RawPsf {
+        glyph_count,
+        width: 8,
+        height,
+        bytes_per_glyph: height,
+        bitmaps,
+        unicode_table
+    })
+}
+
+fn read_psf2(source: &mut impl Read) -> Result<RawPsf, PsfLoadingError> {
+    let mut header = [0u8; 28];
+    source.read_exact(&mut header)?;
+    let field = |idx: usize| u32::from_le_bytes([
+        header[idx * 4], header[idx * 4 + 1], header[idx * 4 + 2], header[idx * 4 + 3]
+    ]);
+    let header_size = field(1) as usize;
+    let flags = field(2);
+    let glyph_count = field(3) as usize;
+    let bytes_per_glyph = field(4) as usize;
+    let height = field(5) as usize;
+    let width = field(6) as usize;
+
+    if width == 0 || height == 0 || width > MAX_GLYPH_SIZE || height > MAX_GLYPH_SIZE ||
+        bytes_per_glyph < height * width.div_ceil(8) {
+        return Err(PsfLoadingError::BadGlyphDimensions(width, height));
+    }
+    if glyph_count > MAX_GLYPH_COUNT {
+        return Err(PsfLoadingError::TooManyGlyphs(glyph_count));
+    }
+
+    // header size includes magic and fields we've already read
+    let already_read = 4 + header.len();
+    if header_size > already_read {
+        let mut padding = vec![0u8; header_size - already_read];
+        source.read_exact(&mut padding)?;
+    }
+
+    // bytes_per_glyph is not bounded by the header, so the buffer only grows with the data actually read
+    let bitmaps_size = glyph_count.checked_mul(bytes_per_glyph)
+        .ok_or(PsfLoadingError::BadGlyphDimensions(width, height))?;
+    let mut bitmaps = Vec::new();
+    source.by_ref().take(bitmaps_size as u64).read_to_end(&mut bitmaps)?;
+    if bitmaps.len() < bitmaps_size {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let unicode_table = if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+        let mut bytes = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        let mut table = vec![Vec::new(); glyph_count];
+        for (glyph_idx, entry) in bytes.split(|it| *it == PSF2_SEPARATOR).take(glyph_count).enumerate() {
+            // everything after a sequence start marker describes combining sequences which we ignore
+            let singles = entry.split(|it| *it == PSF2_START_SEQ).next().unwrap_or(&[]);
+            table[glyph_idx].extend(String::from_utf8_lossy(singles).chars());
+        }
+        Some(table)
+    } else {
+        None
+    };
+
+    Ok(RawPsf {
+        glyph_count,
+        width,
+        height,
+        bytes_per_glyph,
+        bitmaps,
+        unicode_table
+    })
+}
+
+// glyph sizes are bounded by MAX_GLYPH_SIZE, so a row of glyphs always fits in the width of a page
+fn make_font(raw: RawPsf, foreground_color: u8) -> ProportionalFont {
+    let columns = raw.glyph_count.clamp(1, GLYPHS_PER_ROW);
+    let rows_per_page = u16::MAX as usize / raw.height;
+    let glyphs_per_page = columns * rows_per_page;
+    let page_width = columns * raw.width;
+
+    let bytes_per_row = raw.width.div_ceil(8);
+    let mut glyphs = HashMap::new();
+    let mut pages = Vec::new();
+    for first_glyph in (0..raw.glyph_count.max(1)).step_by(glyphs_per_page) {
+        let page_glyph_count = (raw.glyph_count - first_glyph).min(glyphs_per_page);
+        let page_height = page_glyph_count.div_ceil(columns).max(1) * raw.height;
+        let mut page = BlittableSurface::new(page_width as u16, page_height as u16);
+        let buffer = page.get_buffer_mut();
+        for glyph_idx in first_glyph..first_glyph + page_glyph_count {
+            let (x, y) = (
+                ((glyph_idx - first_glyph) % columns) * raw.width,
+                ((glyph_idx - first_glyph) / columns) * raw.height
+            );
+            let bitmap = &raw.bitmaps[glyph_idx * raw.bytes_per_glyph..(glyph_idx + 1) * raw.bytes_per_glyph];
+            for j in 0..raw.height {
+                let stride = (y + j) * page_width + x;
+                for i in 0..raw.width {
+                    let byte = bitmap[j * bytes_per_row + i / 8];
+                    if byte & (0x80 >> (i % 8)) != 0 {
+                        buffer[stride + i] = foreground_color;
+                    }
+                }
+            }
+
+            let glyph = ProportionalGlyph {
+                page: pages.len(),
+                x,
+                y,
+                width: raw.width,
+                height: raw.height,
+                x_offset: 0,
+                y_offset: 0,
+                x_advance: raw.width as i16
+            };
+
+            match &raw.unicode_table {
+                Some(table) => {
+                    for &chr in table[glyph_idx].iter() {
+                        glyphs.entry(chr).or_insert(glyph);
+                    }
+                },
+                None => {
+                    if let Some(chr) = char::from_u32(glyph_idx as u32) {
+                        glyphs.insert(chr, glyph);
+                    }
+                }
+            }
+        }
+        pages.push(page);
+    }
+
+    // console fonts don't carry a base line, so we just assume a typical descent of 1/4 of the height
+    let base_line = raw.height - raw.height / 4;
+    ProportionalFont::new(raw.height, base_line, glyphs, HashMap::new(), pages)
+}
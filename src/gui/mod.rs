@@ -0,0 +1,787 @@
+use std::collections::HashMap;
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, SizedSurface};
+use crate::rendering::draw_list::draw_plain_text;
+use crate::rendering::fonts::rich_text::RichTextFont;
+use crate::rendering::nine_slice::NineSlice;
+use crate::window::{GamepadButton, KeyCode, KeyMod, RetroBlitContext};
+
+/// Widget rects are (x, y, width, height)
+pub type WidgetRect = (i16, i16, u16, u16);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NavCommand {
+    Up,
+    Down,
+    Left,
+    Right,
+    Next,
+    Previous
+}
+
+/// Input of a single gui frame. Read it from the context with Gui::read_input
+/// or fill it by hand to drive the gui from other sources
+#[derive(Clone, Debug, Default)]
+pub struct GuiInput {
+    pub mouse_pos: (i16, i16),
+    pub mouse_down: bool,
+    pub mouse_pressed: bool,
+    pub mouse_released: bool,
+    /// positive scrolls up
+    pub wheel: i16,
+    pub nav: Option<NavCommand>,
+    /// Enter, Space or the gamepad South button
+    pub activate: bool,
+    /// Escape or the gamepad East button
+    pub cancel: bool,
+    pub backspace: bool,
+    pub text: String
+}
+
+/// Palette indices used to draw widgets without nine slices
+#[derive(Copy, Clone, Debug)]
+pub struct GuiPalette {
+    pub text: u8,
+    pub face: u8,
+    pub light: u8,
+    pub shadow: u8,
+    pub field: u8,
+    pub selection: u8,
+    pub selected_text: u8,
+    pub focus: u8
+}
+
+impl Default for GuiPalette {
+    /// picked for the default VGA palette
+    fn default() -> Self {
+        Self {
+            text: 0,
+            face: 7,
+            light: 15,
+            shadow: 8,
+            field: 15,
+            selection: 1,
+            selected_text: 15,
+            focus: 14
+        }
+    }
+}
+
+pub struct GuiStyle<'a> {
+    font: &'a dyn RichTextFont,
+    palette: GuiPalette,
+    panel: Option<NineSlice<'a>>,
+    button: Option<(NineSlice<'a>, NineSlice<'a>)>,
+    padding: u16
+}
+
+impl<'a> GuiStyle<'a> {
+    /// any RichTextFont works, e.g. the tri-spaced Font
+    pub fn new(font: &'a dyn RichTextFont) -> Self {
+        Self {
+            font,
+            palette: GuiPalette::default(),
+            panel: None,
+            button: None,
+            padding: 2
+        }
+    }
+
+    pub fn with_palette(self, palette: GuiPalette) -> Self {
+        Self { palette, ..self }
+    }
+
+    /// panels, lists, text fields and menu popups are drawn with the nine slice instead of bevels
+    pub fn with_panel(self, panel: NineSlice<'a>) -> Self {
+        Self { panel: Some(panel), ..self }
+    }
+
+    /// buttons, menu headers and slider thumbs are drawn with the nine slices instead of bevels
+    pub fn with_button(self, normal: NineSlice<'a>, pressed: NineSlice<'a>) -> Self {
+        Self { button: Some((normal, pressed)), ..self }
+    }
+
+    /// space between widget borders and their content, 2 by default
+    pub fn with_padding(self, padding: u16) -> Self {
+        Self { padding, ..self }
+    }
+
+    pub fn get_font(&self) -> &'a dyn RichTextFont {
+        self.font
+    }
+
+    pub fn get_palette(&self) -> &GuiPalette {
+        &self.palette
+    }
+}
+
+struct OpenMenu {
+    id: usize,
+    highlighted: usize
+}
+
+/// State kept between frames of an immediate mode gui. Widgets are identified by the order
+/// they are called within a frame, so a frame should call them in the same order every time
+/// the layout stays the same.
+/// ```ignore
+/// let input = self.gui.read_input(ctx);
+/// let mut frame = self.gui.begin(ctx, &input, &self.style);
+/// frame.panel((8, 8, 120, 64));
+/// if frame.button((12, 12, 112, 14), "Start") {
+///     ...
+/// }
+/// frame.checkbox((12, 30, 112, 12), "Sound", &mut self.sound_on);
+/// frame.end();
+/// ```
+#[derive(Default)]
+pub struct Gui {
+    focus: Option<usize>,
+    active: Option<usize>,
+    open_menu: Option<OpenMenu>,
+    popup_rect: Option<WidgetRect>,
+    list_scrolls: HashMap<usize, usize>,
+    gamepad_buttons: [bool; GAMEPAD_NAV_BUTTONS.len()]
+}
+
+const GAMEPAD_NAV_BUTTONS: [GamepadButton; 8] = [
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+    GamepadButton::RightShoulder,
+    GamepadButton::LeftShoulder,
+    GamepadButton::South,
+    GamepadButton::East
+];
+
+impl Gui {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// index of the focused widget in the call order
+    pub fn get_focus(&self) -> Option<usize> {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, focus: Option<usize>) {
+        self.focus = focus;
+    }
+
+    /// Reads the mouse, keyboard and gamepad. Gamepad buttons are only reported as pressed,
+    /// so the gui keeps their previous state to react on presses once
+    pub fn read_input(&mut self, ctx: &RetroBlitContext) -> GuiInput {
+        let mut gamepad_just_pressed = [false; GAMEPAD_NAV_BUTTONS.len()];
+        for (idx, &button) in GAMEPAD_NAV_BUTTONS.iter().enumerate() {
+            let pressed = ctx.is_gamepad_button_pressed(button);
+            gamepad_just_pressed[idx] = pressed && !self.gamepad_buttons[idx];
+            self.gamepad_buttons[idx] = pressed;
+        }
+        let [pad_up, pad_down, pad_left, pad_right, pad_next, pad_previous, pad_activate, pad_cancel] =
+            gamepad_just_pressed;
+
+        let nav = if ctx.is_key_just_pressed(KeyCode::Tab) {
+            if ctx.is_key_mod_pressed(KeyMod::Shift) {
+                Some(NavCommand::Previous)
+            } else {
+                Some(NavCommand::Next)
+            }
+        } else if ctx.is_key_just_pressed(KeyCode::Up) || pad_up {
+            Some(NavCommand::Up)
+        } else if ctx.is_key_just_pressed(KeyCode::Down) || pad_down {
+            Some(NavCommand::Down)
+        } else if ctx.is_key_just_pressed(KeyCode::Left) || pad_left {
+            Some(NavCommand::Left)
+        } else if ctx.is_key_just_pressed(KeyCode::Right) || pad_right {
+            Some(NavCommand::Right)
+        } else if pad_next {
+            Some(NavCommand::Next)
+        } else if pad_previous {
+            Some(NavCommand::Previous)
+        } else {
+            None
+        };
+
+        let (mouse_x, mouse_y) = ctx.get_mouse_pos();
+        let wheel = ctx.get_mouse_wheel_delta().1;
+        GuiInput {
+            mouse_pos: (mouse_x.floor() as i16, mouse_y.floor() as i16),
+            mouse_down: ctx.is_mouse_button_pressed(0),
+            mouse_pressed: ctx.is_mouse_button_just_pressed(0),
+            mouse_released: ctx.is_mouse_button_just_released(0),
+            wheel: if wheel > 0.0 { 1 } else if wheel < 0.0 { -1 } else { 0 },
+            nav,
+            activate: ctx.is_key_just_pressed(KeyCode::Enter) ||
+                ctx.is_key_just_pressed(KeyCode::KpEnter) ||
+                ctx.is_key_just_pressed(KeyCode::Space) ||
+                pad_activate,
+            cancel: ctx.is_key_just_pressed(KeyCode::Escape) || pad_cancel,
+            backspace: ctx.is_key_just_pressed(KeyCode::Backspace),
+            text: ctx.get_text_input().to_string()
+        }
+    }
+
+    pub fn begin<'a, 's>(
+        &'a mut self,
+        dest: &'a mut (impl BufferProviderMut<u8> + SizedSurface),
+        input: &'a GuiInput,
+        style: &'a GuiStyle<'s>
+    ) -> GuiFrame<'a, 's> {
+        let (width, height) = (dest.get_width(), dest.get_height());
+        GuiFrame {
+            gui: self,
+            buffer: dest.get_buffer_mut(),
+            width,
+            height,
+            input,
+            style,
+            pending_nav: input.nav,
+            widget_count: 0,
+            mouse_taken: false,
+            popup: None
+        }
+    }
+}
+
+struct Popup {
+    rect: WidgetRect,
+    items: Vec<String>,
+    highlighted: usize
+}
+
+struct Interaction {
+    id: usize,
+    hovered: bool,
+    pressed: bool,
+    clicked: bool,
+    focused: bool
+}
+
+/// Widgets of a single frame, drawn immediately in the call order. Menu popups are drawn
+/// on top of everything by end()
+pub struct GuiFrame<'a, 's> {
+    gui: &'a mut Gui,
+    buffer: &'a mut [u8],
+    width: usize,
+    height: usize,
+    input: &'a GuiInput,
+    style: &'a GuiStyle<'s>,
+    /// navigation not consumed by the focused widget moves the focus at the end of the frame
+    pending_nav: Option<NavCommand>,
+    widget_count: usize,
+    mouse_taken: bool,
+    popup: Option<Popup>
+}
+
+impl<'a, 's> GuiFrame<'a, 's> {
+    pub fn label(&mut self, position: (i16, i16), text: &str) {
+        let color = self.style.palette.text;
+        draw_plain_text(self.buffer, self.width, self.style.font, position, text, Some(color));
+    }
+
+    pub fn panel(&mut self, rect: WidgetRect) {
+        match self.style.panel {
+            Some(panel) => self.draw_nine_slice(&panel, rect),
+            None => {
+                let palette = self.style.palette;
+                self.fill_rect(rect, palette.face);
+                self.draw_bevel(rect, palette.light, palette.shadow);
+            }
+        }
+    }
+
+    /// true when clicked or activated with the focus on it
+    pub fn button(&mut self, rect: WidgetRect, text: &str) -> bool {
+        let interaction = self.interact(rect);
+        self.draw_button_face(rect, interaction.pressed);
+        let shift = if interaction.pressed { 1 } else { 0 };
+        let text_rect = (rect.0 + shift, rect.1 + shift, rect.2, rect.3);
+        self.draw_text_centered(text_rect, text, self.style.palette.text);
+        self.draw_focus(&interaction, rect);
+        interaction.clicked || (interaction.focused && self.input.activate)
+    }
+
+    /// the box is as tall as the rect, the text goes to the right of it.
+    /// Returns true when the value changed
+    pub fn checkbox(&mut self, rect: WidgetRect, text: &str, value: &mut bool) -> bool {
+        let interaction = self.interact(rect);
+        let changed = interaction.clicked || (interaction.focused && self.input.activate);
+        if changed {
+            *value = !*value;
+        }
+        let palette = self.style.palette;
+        let box_rect = (rect.0, rect.1, rect.3, rect.3);
+        self.fill_rect(box_rect, palette.field);
+        self.draw_bevel(box_rect, palette.shadow, palette.light);
+        if *value && rect.3 > 6 {
+            self.fill_rect((rect.0 + 3, rect.1 + 3, rect.3 - 6, rect.3 - 6), palette.text);
+        }
+        let text_x = rect.0 + rect.3 as i16 + self.style.padding as i16;
+        let text_rect = (text_x, rect.1, rect.2.saturating_sub(rect.3 + self.style.padding), rect.3);
+        self.draw_text_left(text_rect, text, palette.text);
+        self.draw_focus(&interaction, rect);
+        changed
+    }
+
+    /// Dragged with the mouse or moved by step with Left and Right while focused.
+    /// A step of 0 doesn't snap the dragged value. Returns true when the value changed
+    pub fn slider(&mut self, rect: WidgetRect, value: &mut f32, min: f32, max: f32, step: f32) -> bool {
+        let interaction = self.interact(rect);
+        let old_value = *value;
+        let thumb_width = rect.3.min(rect.2) / 2;
+        let track_width = rect.2.saturating_sub(thumb_width).max(1);
+
+        if self.gui.active == Some(interaction.id) && self.input.mouse_down {
+            let t = (self.input.mouse_pos.0 - rect.0 - (thumb_width / 2) as i16) as f32 / track_width as f32;
+            let mut new_value = min + t.clamp(0.0, 1.0) * (max - min);
+            if step > 0.0 {
+                new_value = min + ((new_value - min) / step).round() * step;
+            }
+            *value = new_value;
+        }
+        if interaction.focused {
+            match self.pending_nav {
+                Some(NavCommand::Left) => {
+                    *value -= step;
+                    self.pending_nav = None;
+                },
+                Some(NavCommand::Right) => {
+                    *value += step;
+                    self.pending_nav = None;
+                },
+                _ => {}
+            }
+        }
+        *value = value.clamp(min.min(max), max.max(min));
+
+        let palette = self.style.palette;
+        let track_y = rect.1 + rect.3 as i16 / 2 - 1;
+        self.fill_rect((rect.0, track_y, rect.2, 2), palette.shadow);
+        let t = if max != min { (*value - min) / (max - min) } else { 0.0 };
+        let thumb_x = rect.0 + (t * track_width as f32).round() as i16;
+        self.draw_button_face((thumb_x, rect.1, thumb_width, rect.3), self.gui.active == Some(interaction.id));
+        self.draw_focus(&interaction, rect);
+        *value != old_value
+    }
+
+    /// A scrolled list with a single selected item. Up and Down move the selection while focused
+    /// and pass the focus on at the ends. Returns true when the selection changed
+    pub fn list(&mut self, rect: WidgetRect, items: &[&str], selected: &mut usize) -> bool {
+        let interaction = self.interact(rect);
+        let old_selected = *selected;
+        let row_height = self.style.font.line_height().max(1) as i16;
+        let padding = self.style.padding as i16;
+        let visible_rows = ((rect.3 as i16 - padding * 2) / row_height).max(1) as usize;
+        let max_scroll = items.len().saturating_sub(visible_rows);
+        let mut scroll = self.gui.list_scrolls.get(&interaction.id).copied().unwrap_or(0);
+
+        if interaction.hovered {
+            if self.input.wheel > 0 {
+                scroll = scroll.saturating_sub(1);
+            } else if self.input.wheel < 0 {
+                scroll += 1;
+            }
+            if interaction.clicked {
+                let row = (self.input.mouse_pos.1 - rect.1 - padding) / row_height;
+                if row >= 0 && scroll + (row as usize) < items.len() {
+                    *selected = scroll + row as usize;
+                }
+            }
+        }
+        if interaction.focused && !items.is_empty() {
+            match self.pending_nav {
+                Some(NavCommand::Up) if *selected > 0 => {
+                    *selected = (*selected).min(items.len()) - 1;
+                    self.pending_nav = None;
+                },
+                Some(NavCommand::Down) if *selected + 1 < items.len() => {
+                    *selected += 1;
+                    self.pending_nav = None;
+                },
+                _ => {}
+            }
+        }
+        if *selected != old_selected {
+            // keeps the selection in view after keyboard moves
+            scroll = scroll.min(*selected).max((*selected + 1).saturating_sub(visible_rows));
+        }
+        scroll = scroll.min(max_scroll);
+        self.gui.list_scrolls.insert(interaction.id, scroll);
+
+        self.draw_field(rect);
+        let palette = self.style.palette;
+        let row_width = rect.2.saturating_sub(self.style.padding * 2);
+        for (row, item) in items.iter().enumerate().skip(scroll).take(visible_rows) {
+            let row_rect = (
+                rect.0 + padding,
+                rect.1 + padding + (row - scroll) as i16 * row_height,
+                row_width,
+                row_height as u16
+            );
+            let color = if row == *selected {
+                self.fill_rect(row_rect, palette.selection);
+                palette.selected_text
+            } else {
+                palette.text
+            };
+            self.draw_text_left(row_rect, item, color);
+        }
+        self.draw_focus(&interaction, rect);
+        *selected != old_selected
+    }
+
+    /// Single line text input, typed characters go to the end while focused.
+    /// Returns true when the text changed
+    pub fn text_field(&mut self, rect: WidgetRect, text: &mut String, max_chars: usize) -> bool {
+        let interaction = self.interact(rect);
+        let mut changed = false;
+        if interaction.focused {
+            if self.input.backspace && text.pop().is_some() {
+                changed = true;
+            }
+            for chr in self.input.text.chars() {
+                if text.chars().count() < max_chars {
+                    text.push(chr);
+                    changed = true;
+                }
+            }
+        }
+
+        self.draw_field(rect);
+        let padding = self.style.padding;
+        let font = self.style.font;
+        // the caret takes a pixel and a gap, the tail of a long text stays visible
+        let available = rect.2.saturating_sub(padding * 2 + 2) as i16;
+        let mut visible: &str = text;
+        while measure_text(font, visible) > available {
+            let mut chars = visible.chars();
+            chars.next();
+            visible = chars.as_str();
+        }
+        let text_rect = (rect.0 + padding as i16, rect.1, rect.2.saturating_sub(padding * 2), rect.3);
+        self.draw_text_left(text_rect, visible, self.style.palette.text);
+        if interaction.focused {
+            let line_height = font.line_height() as u16;
+            let caret_x = text_rect.0 + measure_text(font, visible) + 1;
+            let caret_y = rect.1 + (rect.3 as i16 - line_height as i16) / 2;
+            self.fill_rect((caret_x, caret_y, 1, line_height), self.style.palette.text);
+        }
+        self.draw_focus(&interaction, rect);
+        changed
+    }
+
+    /// A header opening a popup with items below it. While open, Up and Down move the highlight
+    /// and activation picks it. Returns the picked item
+    pub fn menu(&mut self, rect: WidgetRect, text: &str, items: &[&str]) -> Option<usize> {
+        let interaction = self.interact(rect);
+        let is_open = matches!(self.gui.open_menu, Some(OpenMenu { id, .. }) if id == interaction.id);
+        let header_hit = (interaction.hovered && self.input.mouse_pressed) ||
+            (interaction.focused && self.input.activate && !is_open);
+
+        self.draw_button_face(rect, is_open);
+        self.draw_text_centered(rect, text, self.style.palette.text);
+        self.draw_focus(&interaction, rect);
+
+        if !is_open {
+            if header_hit && !items.is_empty() {
+                self.gui.open_menu = Some(OpenMenu { id: interaction.id, highlighted: 0 });
+                self.popup = Some(Popup {
+                    rect: self.popup_rect(rect, items),
+                    items: items.iter().map(|it| it.to_string()).collect(),
+                    highlighted: 0
+                });
+            }
+            return None;
+        }
+        if header_hit {
+            self.gui.open_menu = None;
+            return None;
+        }
+
+        let popup_rect = self.popup_rect(rect, items);
+        let row_height = self.style.font.line_height().max(1) as i16;
+        let padding = self.style.padding as i16;
+        let mut highlighted = self.gui.open_menu.as_ref().map_or(0, |it| it.highlighted).min(items.len() - 1);
+        let mut picked = None;
+        let mut close = false;
+
+        if contains(popup_rect, self.input.mouse_pos) {
+            self.mouse_taken |= self.input.mouse_pressed;
+            let row = (self.input.mouse_pos.1 - popup_rect.1 - padding) / row_height;
+            if row >= 0 && (row as usize) < items.len() {
+                highlighted = row as usize;
+                if self.input.mouse_released {
+                    picked = Some(highlighted);
+                }
+            }
+        } else if self.input.mouse_pressed && !interaction.hovered {
+            close = true;
+        }
+        match self.pending_nav {
+            Some(NavCommand::Up) => {
+                highlighted = (highlighted + items.len() - 1) % items.len();
+                self.pending_nav = None;
+            },
+            Some(NavCommand::Down) => {
+                highlighted = (highlighted + 1) % items.len();
+                self.pending_nav = None;
+            },
+            _ => {}
+        }
+        if self.input.activate {
+            picked = Some(highlighted);
+        }
+        if self.input.cancel || picked.is_some() {
+            close = true;
+        }
+
+        if close {
+            self.gui.open_menu = None;
+        } else {
+            self.gui.open_menu = Some(OpenMenu { id: interaction.id, highlighted });
+            self.popup = Some(Popup {
+                rect: popup_rect,
+                items: items.iter().map(|it| it.to_string()).collect(),
+                highlighted
+            });
+        }
+        picked
+    }
+
+    /// Finishes the frame: draws the open menu popup and moves the focus
+    /// by navigation no widget has consumed
+    pub fn end(mut self) {
+        if let Some(popup) = self.popup.take() {
+            self.draw_popup(&popup);
+            self.gui.popup_rect = Some(popup.rect);
+        } else {
+            self.gui.popup_rect = None;
+            self.gui.open_menu = None;
+        }
+
+        if self.input.mouse_pressed && !self.mouse_taken {
+            self.gui.focus = None;
+        }
+        if self.input.mouse_released || !self.input.mouse_down {
+            self.gui.active = None;
+        }
+
+        let count = self.widget_count;
+        if count == 0 {
+            self.gui.focus = None;
+            return;
+        }
+        self.gui.focus = match (self.pending_nav, self.gui.focus) {
+            (Some(NavCommand::Down), Some(focus)) | (Some(NavCommand::Next), Some(focus)) =>
+                Some((focus + 1) % count),
+            (Some(NavCommand::Up), Some(focus)) | (Some(NavCommand::Previous), Some(focus)) =>
+                Some((focus + count - 1) % count),
+            (Some(NavCommand::Down), None) | (Some(NavCommand::Next), None) => Some(0),
+            (Some(NavCommand::Up), None) | (Some(NavCommand::Previous), None) => Some(count - 1),
+            (_, focus) => focus.filter(|&it| it < count)
+        };
+    }
+
+    fn interact(&mut self, rect: WidgetRect) -> Interaction {
+        let id = self.widget_count;
+        self.widget_count += 1;
+
+        let owns_popup = matches!(self.gui.open_menu, Some(OpenMenu { id: menu_id, .. }) if menu_id == id);
+        let under_popup = self.gui.popup_rect.is_some_and(|it| contains(it, self.input.mouse_pos));
+        let hovered = contains(rect, self.input.mouse_pos) && (owns_popup || !under_popup);
+        if hovered && self.input.mouse_pressed {
+            self.gui.active = Some(id);
+            self.gui.focus = Some(id);
+            self.mouse_taken = true;
+        }
+        let active = self.gui.active == Some(id);
+        Interaction {
+            id,
+            hovered,
+            pressed: active && hovered && self.input.mouse_down,
+            clicked: active && hovered && self.input.mouse_released,
+            focused: self.gui.focus == Some(id)
+        }
+    }
+
+    fn popup_rect(&self, header: WidgetRect, items: &[&str]) -> WidgetRect {
+        let padding = self.style.padding as i16;
+        let widest = items.iter().map(|it| measure_text(self.style.font, it)).max().unwrap_or(0);
+        let width = (widest + padding * 2).max(header.2 as i16) as u16;
+        let height = (items.len() * self.style.font.line_height()) as u16 + self.style.padding * 2;
+        (header.0, header.1 + header.3 as i16, width, height)
+    }
+
+    fn draw_popup(&mut self, popup: &Popup) {
+        self.draw_field(popup.rect);
+        let palette = self.style.palette;
+        let padding = self.style.padding as i16;
+        let row_height = self.style.font.line_height() as i16;
+        for (row, item) in popup.items.iter().enumerate() {
+            let row_rect = (
+                popup.rect.0 + padding,
+                popup.rect.1 + padding + row as i16 * row_height,
+                popup.rect.2.saturating_sub(self.style.padding * 2),
+                row_height as u16
+            );
+            let color = if row == popup.highlighted {
+                self.fill_rect(row_rect, palette.selection);
+                palette.selected_text
+            } else {
+                palette.text
+            };
+            self.draw_text_left(row_rect, item, color);
+        }
+    }
+
+    fn draw_button_face(&mut self, rect: WidgetRect, pressed: bool) {
+        match self.style.button {
+            Some((normal, pressed_face)) => {
+                let face = if pressed { pressed_face } else { normal };
+                self.draw_nine_slice(&face, rect);
+            },
+            None => {
+                let palette = self.style.palette;
+                self.fill_rect(rect, palette.face);
+                if pressed {
+                    self.draw_bevel(rect, palette.shadow, palette.light);
+                } else {
+                    self.draw_bevel(rect, palette.light, palette.shadow);
+                }
+            }
+        }
+    }
+
+    fn draw_field(&mut self, rect: WidgetRect) {
+        match self.style.panel {
+            Some(panel) => self.draw_nine_slice(&panel, rect),
+            None => {
+                let palette = self.style.palette;
+                self.fill_rect(rect, palette.field);
+                self.draw_bevel(rect, palette.shadow, palette.light);
+            }
+        }
+    }
+
+    fn draw_focus(&mut self, interaction: &Interaction, rect: WidgetRect) {
+        if !interaction.focused {
+            return;
+        }
+        let color = self.style.palette.focus;
+        let (x, y, w, h) = rect;
+        if w < 2 || h < 2 {
+            return;
+        }
+        self.fill_rect((x - 1, y - 1, w + 2, 1), color);
+        self.fill_rect((x - 1, y + h as i16, w + 2, 1), color);
+        self.fill_rect((x - 1, y, 1, h), color);
+        self.fill_rect((x + w as i16, y, 1, h), color);
+    }
+
+    fn draw_nine_slice(&mut self, nine_slice: &NineSlice, rect: WidgetRect) {
+        let mut dest = FrameBuffer { buffer: &mut *self.buffer, width: self.width, height: self.height };
+        nine_slice.draw(&mut dest, rect.0, rect.1, rect.2 as usize, rect.3 as usize);
+    }
+
+    fn draw_bevel(&mut self, rect: WidgetRect, top_left: u8, bottom_right: u8) {
+        let (x, y, w, h) = rect;
+        if w == 0 || h == 0 {
+            return;
+        }
+        self.fill_rect((x, y, w, 1), top_left);
+        self.fill_rect((x, y, 1, h), top_left);
+        self.fill_rect((x, y + h as i16 - 1, w, 1), bottom_right);
+        self.fill_rect((x + w as i16 - 1, y, 1, h), bottom_right);
+    }
+
+    fn fill_rect(&mut self, rect: WidgetRect, color: u8) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let left = (rect.0 as i32).clamp(0, width);
+        let right = (rect.0 as i32 + rect.2 as i32).clamp(0, width);
+        let top = (rect.1 as i32).clamp(0, height);
+        let bottom = (rect.1 as i32 + rect.3 as i32).clamp(0, height);
+        if left >= right {
+            return;
+        }
+        for row in top..bottom {
+            let stride = (row * width) as usize;
+            for px in &mut self.buffer[stride + left as usize..stride + right as usize] {
+                *px = color;
+            }
+        }
+    }
+
+    fn draw_text_left(&mut self, rect: WidgetRect, text: &str, color: u8) {
+        let font = self.style.font;
+        let text = fit_text(font, text, rect.2 as i16);
+        let y = rect.1 + (rect.3 as i16 - font.line_height() as i16) / 2;
+        draw_plain_text(self.buffer, self.width, font, (rect.0, y), text, Some(color));
+    }
+
+    fn draw_text_centered(&mut self, rect: WidgetRect, text: &str, color: u8) {
+        let font = self.style.font;
+        let padding = self.style.padding as i16;
+        let text = fit_text(font, text, rect.2 as i16 - padding * 2);
+        let x = rect.0 + (rect.2 as i16 - measure_text(font, text)) / 2;
+        let y = rect.1 + (rect.3 as i16 - font.line_height() as i16) / 2;
+        draw_plain_text(self.buffer, self.width, font, (x, y), text, Some(color));
+    }
+}
+
+/// lets nine slices draw into the frame buffer
+struct FrameBuffer<'a> {
+    buffer: &'a mut [u8],
+    width: usize,
+    height: usize
+}
+
+impl<'a> SizedSurface for FrameBuffer<'a> {
+    fn get_width(&self) -> usize { self.width }
+
+    fn get_height(&self) -> usize { self.height }
+}
+
+impl<'a> BufferProvider<u8> for FrameBuffer<'a> {
+    fn get_buffer(&self) -> &[u8] {
+        self.buffer
+    }
+}
+
+impl<'a> BufferProviderMut<u8> for FrameBuffer<'a> {
+    fn get_buffer_mut(&mut self) -> &mut [u8] {
+        self.buffer
+    }
+}
+
+fn contains(rect: WidgetRect, point: (i16, i16)) -> bool {
+    point.0 >= rect.0 && point.1 >= rect.1 &&
+        (point.0 as i32) < rect.0 as i32 + rect.2 as i32 &&
+        (point.1 as i32) < rect.1 as i32 + rect.3 as i32
+}
+
+/// width of a single line of plain text
+pub fn measure_text(font: &dyn RichTextFont, text: &str) -> i16 {
+    let mut width = 0;
+    let mut previous = None;
+    for chr in text.chars() {
+        if chr == ' ' {
+            width += font.space_width() as i16;
+            previous = None;
+            continue;
+        }
+        if let Some(previous) = previous {
+            width += font.kerning(previous, chr);
+        }
+        width += font.char_advance(chr);
+        previous = Some(chr);
+    }
+    width
+}
+
+/// the longest prefix fitting into the width
+fn fit_text<'t>(font: &dyn RichTextFont, text: &'t str, max_width: i16) -> &'t str {
+    let mut end = text.len();
+    while end > 0 && measure_text(font, &text[..end]) > max_width {
+        end = text[..end].char_indices().last().map_or(0, |(idx, _)| idx);
+    }
+    &text[..end]
+}
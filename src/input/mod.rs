@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use thiserror::Error;
+use crate::window::{GamepadAxis, GamepadButton, KeyCode, RetroBlitContext};
+
+#[derive(Error, Debug)]
+pub enum InputBindingsLoadingError {
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("Int parse failed")]
+    FailedToParseInt(#[from] std::num::ParseIntError),
+    #[error("Found bad entry at line {0}")]
+    FoundBadEntry(usize),
+    #[error("Expected action or axis name at line {0}")]
+    NameExpected(usize),
+    #[error("Unknown input source '{0}'")]
+    UnknownInputSource(String),
+    #[error("Unknown key name '{0}'")]
+    UnknownKeyName(String),
+    #[error("Unknown gamepad button name '{0}'")]
+    UnknownGamepadButtonName(String),
+    #[error("Unknown gamepad axis name '{0}'")]
+    UnknownGamepadAxisName(String)
+}
+
+/// A digital input which can be bound to an action
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InputSource {
+    Key(KeyCode),
+    /// 0 is left, 1 is middle and 2 is right
+    MouseButton(u8),
+    GamepadButton(GamepadButton)
+}
+
+impl InputSource {
+    fn is_pressed(&self, ctx: &RetroBlitContext) -> bool {
+        match *self {
+            InputSource::Key(key_code) => ctx.is_key_pressed(key_code),
+            InputSource::MouseButton(button_number) => ctx.is_mouse_button_pressed(button_number),
+            InputSource::GamepadButton(button) => ctx.is_gamepad_button_pressed(button)
+        }
+    }
+}
+
+/// An input which can be bound to an axis. Axis value is always in a range of -1.0..=1.0
+#[derive(Copy, Clone, PartialEq)]
+pub enum AxisSource {
+    /// gives -1.0 when negative is pressed, 1.0 when positive is pressed
+    /// and 0.0 when both or none of them are pressed
+    Digital { negative: InputSource, positive: InputSource },
+    Analog(GamepadAxis)
+}
+
+#[derive(Copy, Clone, Default)]
+struct ActionState {
+    pressed: bool,
+    was_pressed: bool
+}
+
+struct Action {
+    bindings: Vec<InputSource>,
+    state: ActionState
+}
+
+struct Axis {
+    bindings: Vec<AxisSource>,
+    value: f32
+}
+
+/// Maps named actions and axes onto raw inputs.
+///
+/// Call update once per frame before querying, e.g. at the start of ContextHandler::update.
+/// Actions are considered pressed if any of their bindings is pressed,
+/// axes take the value with the largest magnitude among their bindings.
+pub struct ActionMap {
+    actions: HashMap<String, Action>,
+    axes: HashMap<String, Axis>,
+    dead_zone: f32
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self {
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+            dead_zone: 0.15
+        }
+    }
+
+    pub fn with_action(mut self, name: &str, bindings: &[InputSource]) -> Self {
+        for &binding in bindings {
+            self.bind_action(name, binding);
+        }
+        self
+    }
+
+    pub fn with_axis(mut self, name: &str, bindings: &[AxisSource]) -> Self {
+        for &binding in bindings {
+            self.bind_axis(name, binding);
+        }
+        self
+    }
+
+    /// analog values with a magnitude below the dead zone are treated as 0.0
+    pub fn with_dead_zone(self, dead_zone: f32) -> Self {
+        Self { dead_zone, ..self }
+    }
+
+    /// Names go to the saved bindings as single words, so they must not be empty,
+    /// contain whitespace or start with '#'. Panics otherwise
+    pub fn bind_action(&mut self, name: &str, source: InputSource) {
+        check_binding_name(name);
+        let action = self.actions
+            .entry(name.to_string())
+            .or_insert_with(|| Action { bindings: Vec::new(), state: ActionState::default() });
+        if !action.bindings.contains(&source) {
+            action.bindings.push(source);
+        }
+    }
+
+    pub fn unbind_action(&mut self, name: &str, source: InputSource) {
+        if let Some(action) = self.actions.get_mut(name) {
+            action.bindings.retain(|it| *it != source);
+        }
+    }
+
+    /// the same naming rules as for bind_action apply
+    pub fn bind_axis(&mut self, name: &str, source: AxisSource) {
+        check_binding_name(name);
+        let axis = self.axes
+            .entry(name.to_string())
+            .or_insert_with(|| Axis { bindings: Vec::new(), value: 0.0 });
+        if !axis.bindings.contains(&source) {
+            axis.bindings.push(source);
+        }
+    }
+
+    pub fn unbind_axis(&mut self, name: &str, source: AxisSource) {
+        if let Some(axis) = self.axes.get_mut(name) {
+            axis.bindings.retain(|it| *it != source);
+        }
+    }
+
+    /// removes all bindings of an action or an axis, but keeps it's name registered
+    pub fn clear_bindings(&mut self, name: &str) {
+        if let Some(action) = self.actions.get_mut(name) {
+            action.bindings.clear();
+        }
+        if let Some(axis) = self.axes.get_mut(name) {
+            axis.bindings.clear();
+        }
+    }
+
+    pub fn get_action_bindings(&self, name: &str) -> &[InputSource] {
+        self.actions
+            .get(name)
+            .map(|it| &it.bindings[..])
+            .unwrap_or(&[])
+    }
+
+    pub fn get_axis_bindings(&self, name: &str) -> &[AxisSource] {
+        self.axes
+            .get(name)
+            .map(|it| &it.bindings[..])
+            .unwrap_or(&[])
+    }
+
+    pub fn update(&mut self, ctx: &RetroBlitContext) {
+        for action in self.actions.values_mut() {
+            action.state.was_pressed = action.state.pressed;
+            action.state.pressed = action.bindings
+                .iter()
+                .any(|it| it.is_pressed(ctx));
+        }
+
+        let dead_zone = self.dead_zone;
+        for axis in self.axes.values_mut() {
+            axis.value = axis.bindings
+                .iter()
+                .map(|it| match *it {
+                    AxisSource::Digital { negative, positive } => {
+                        match (negative.is_pressed(ctx), positive.is_pressed(ctx)) {
+                            (true, false) => -1.0,
+                            (false, true) => 1.0,
+                            _ => 0.0
+                        }
+                    },
+                    AxisSource::Analog(gamepad_axis) => {
+                        let value = ctx.get_gamepad_axis(gamepad_axis);
+                        if value.abs() < dead_zone { 0.0 } else { value }
+                    }
+                })
+                .fold(0.0f32, |acc, next| if next.abs() > acc.abs() { next } else { acc });
+        }
+    }
+
+    pub fn is_pressed(&self, name: &str) -> bool {
+        self.get_action_state(name).pressed
+    }
+
+    pub fn is_just_pressed(&self, name: &str) -> bool {
+        let state = self.get_action_state(name);
+        state.pressed && !state.was_pressed
+    }
+
+    pub fn is_just_released(&self, name: &str) -> bool {
+        let state = self.get_action_state(name);
+        !state.pressed && state.was_pressed
+    }
+
+    pub fn get_axis(&self, name: &str) -> f32 {
+        self.axes
+            .get(name)
+            .map(|it| it.value)
+            .unwrap_or(0.0)
+    }
+
+    fn get_action_state(&self, name: &str) -> ActionState {
+        self.actions
+            .get(name)
+            .map(|it| it.state)
+            .unwrap_or_default()
+    }
+
+    /// Serializes bindings to a simple line based text format:
+    ///
+    /// ```text
+    /// # comment
+    /// action fire key:Space mouse:0 gamepad:South
+    /// axis turn key:A|key:D gamepad_axis:LeftStickX
+    /// ```
+    pub fn save_bindings_to(&self, mut destination: impl Write) -> std::io::Result<()> {
+        let mut action_names = self.actions.keys().collect::<Vec<_>>();
+        action_names.sort();
+        for name in action_names {
+            write!(destination, "action {}", name)?;
+            for source in self.actions[name].bindings.iter() {
+                write!(destination, " {}", format_input_source(source))?;
+            }
+            writeln!(destination)?;
+        }
+
+        let mut axis_names = self.axes.keys().collect::<Vec<_>>();
+        axis_names.sort();
+        for name in axis_names {
+            write!(destination, "axis {}", name)?;
+            for source in self.axes[name].bindings.iter() {
+                match source {
+                    AxisSource::Digital { negative, positive } => write!(
+                        destination, " {}|{}",
+                        format_input_source(negative),
+                        format_input_source(positive)
+                    )?,
+                    AxisSource::Analog(gamepad_axis) => write!(
+                        destination, " gamepad_axis:{}",
+                        gamepad_axis_name(*gamepad_axis)
+                    )?
+                }
+            }
+            writeln!(destination)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces bindings of all actions and axes mentioned in the source.
+    /// Actions and axes which are not mentioned keep their current bindings,
+    /// an action and an axis of the same name are independent
+    pub fn load_bindings_from(&mut self, mut source: impl Read) -> Result<(), InputBindingsLoadingError> {
+        let mut content = String::new();
+        source.read_to_string(&mut content)?;
+
+        // several lines of the same name add up
+        let mut loaded_actions = HashSet::new();
+        let mut loaded_axes = HashSet::new();
+
+        for (line_idx, line) in content.lines().enumerate() {
+            let line_number = line_idx + 1;
+            let mut splitted = line.split_whitespace();
+            let leading = match splitted.next() {
+                None => continue,
+                Some(leading) if leading.starts_with("#") => continue,
+                Some(leading) => leading
+            };
+            let name = splitted
+                .next()
+                .ok_or(InputBindingsLoadingError::NameExpected(line_number))?;
+            match leading {
+                "action" => {
+                    let mut bindings = Vec::new();
+                    for token in splitted {
+                        bindings.push(parse_input_source(token)?);
+                    }
+                    if loaded_actions.insert(name) {
+                        if let Some(action) = self.actions.get_mut(name) {
+                            action.bindings.clear();
+                        }
+                    }
+                    for binding in bindings {
+                        self.bind_action(name, binding);
+                    }
+                },
+                "axis" => {
+                    let mut bindings = Vec::new();
+                    for token in splitted {
+                        bindings.push(parse_axis_source(token)?);
+                    }
+                    if loaded_axes.insert(name) {
+                        if let Some(axis) = self.axes.get_mut(name) {
+                            axis.bindings.clear();
+                        }
+                    }
+                    for binding in bindings {
+                        self.bind_axis(name, binding);
+                    }
+                },
+                _ => return Err(InputBindingsLoadingError::FoundBadEntry(line_number))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_binding_name(name: &str) {
+    assert!(
+        !name.is_empty() && !name.starts_with('#') && !name.contains(char::is_whitespace),
+        "bad action or axis name '{}'", name
+    );
+}
+
+fn format_input_source(source: &InputSource) -> String {
+    match *source {
+        InputSource::Key(key_code) => format!("key:{}", key_code_name(key_code)),
+        InputSource::MouseButton(button_number) => format!("mouse:{}", button_number),
+        InputSource::GamepadButton(button) => format!("gamepad:{}", gamepad_button_name(button))
+    }
+}
+
+fn parse_input_source(token: &str) -> Result<InputSource, InputBindingsLoadingError> {
+    let (kind, value) = token
+        .split_once(':')
+        .ok_or_else(|| InputBindingsLoadingError::UnknownInputSource(token.to_string()))?;
+    match kind {
+        "key" => key_code_from_name(value)
+            .map(InputSource::Key)
+            .ok_or_else(|| InputBindingsLoadingError::UnknownKeyName(value.to_string())),
+        "mouse" => Ok(InputSource::MouseButton(value.parse()?)),
+        "gamepad" => gamepad_button_from_name(value)
+            .map(InputSource::GamepadButton)
+            .ok_or_else(|| InputBindingsLoadingError::UnknownGamepadButtonName(value.to_string())),
+        _ => Err(InputBindingsLoadingError::UnknownInputSource(token.to_string()))
+    }
+}
+
+fn parse_axis_source(token: &str) -> Result<AxisSource, InputBindingsLoadingError> {
+    if let Some((negative, positive)) = token.split_once('|') {
+        return Ok(AxisSource::Digital {
+            negative: parse_input_source(negative)?,
+            positive: parse_input_source(positive)?
+        });
+    }
+    match token.split_once(':') {
+        Some(("gamepad_axis", value)) => gamepad_axis_from_name(value)
+            .map(AxisSource::Analog)
+            .ok_or_else(|| InputBindingsLoadingError::UnknownGamepadAxisName(value.to_string())),
+        _ => Err(InputBindingsLoadingError::UnknownInputSource(token.to_string()))
+    }
+}
+
+// Names are the variant names. The match makes the compiler reject a variant missing from the list,
+// so every binding can be saved and loaded back
+macro_rules! named_variants {
+    ($ty:ident, $table:ident, $name_fn:ident, $from_name_fn:ident { $($variant:ident),* }) => {
+        const $table: &[($ty, &str)] = &[$(($ty::$variant, stringify!($variant))),*];
+
+        pub fn $name_fn(value: $ty) -> &'static str {
+            match value {
+                $($ty::$variant => stringify!($variant)),*
+            }
+        }
+
+        pub fn $from_name_fn(name: &str) -> Option<$ty> {
+            $table
+                .iter()
+                .find(|(_, it)| *it == name)
+                .map(|(value, _)| *value)
+        }
+    };
+}
+
+named_variants!(GamepadButton, GAMEPAD_BUTTON_NAMES, gamepad_button_name, gamepad_button_from_name {
+    South, East, West, North, LeftShoulder, RightShoulder, LeftStick, RightStick, Start, Select,
+    DPadUp, DPadDown, DPadLeft, DPadRight
+});
+
+named_variants!(GamepadAxis, GAMEPAD_AXIS_NAMES, gamepad_axis_name, gamepad_axis_from_name {
+    LeftStickX, LeftStickY, RightStickX, RightStickY, LeftTrigger, RightTrigger
+});
+
+named_variants!(KeyCode, KEY_CODE_NAMES, key_code_name, key_code_from_name {
+    Space, Apostrophe, Comma, Minus, Period, Slash, Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7,
+    Key8, Key9, Semicolon, Equal, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V,
+    W, X, Y, Z, LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2, Escape, Enter,
+    Tab, Backspace, Insert, Delete, Right, Left, Down, Up, PageUp, PageDown, Home, End, CapsLock,
+    ScrollLock, NumLock, PrintScreen, Pause, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13,
+    F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25, Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6,
+    Kp7, Kp8, Kp9, KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual, LeftShift,
+    LeftControl, LeftAlt, LeftSuper, RightShift, RightControl, RightAlt, RightSuper, Menu
+});
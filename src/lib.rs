@@ -1,6 +1,11 @@
 pub mod window;
+pub mod input;
 pub mod format_loaders;
 pub mod rendering;
+pub mod gui;
 // pub mod audio;
 pub mod utility;
-pub mod math_utils;
\ No newline at end of file
+pub mod math_utils;
+pub mod tween;
+pub mod transitions;
+pub mod scenes;
\ No newline at end of file
@@ -1,3 +1,6 @@
+use glam::{Mat3A, vec2, vec3a};
+use crate::rendering::transform::Transform;
+
 pub struct Rect {
     pub x_range: std::ops::Range<usize>,
     pub y_range: std::ops::Range<usize>
@@ -28,9 +31,24 @@ pub trait Blittable<T: Copy> : SizedSurface + BufferProvider<T> {
     #[inline(always)]
     fn blend_function(&self, dst: &mut T, src: &T) { *dst = *src; }
 
-    fn blit_impl(&self, buffer: &mut [T], buffer_width: usize, self_rect: Rect, dst_rect: Rect, flip: Flip) {
-        let src_rect = self_rect;
-        let dst_rect = dst_rect;
+    /// blends a whole row, dst and src are of the same length.
+    /// Override it when rows can be processed in bulk, e.g. for opaque copies or lookup tables
+    #[inline(always)]
+    fn blend_row(&self, dst: &mut [T], src: &[T]) {
+        for (dst, src) in dst.iter_mut().zip(src.iter()) {
+            self.blend_function(dst, src);
+        }
+    }
+
+    /// the same as blend_row, but src is read backwards. Used for flips by x
+    #[inline(always)]
+    fn blend_row_reversed(&self, dst: &mut [T], src: &[T]) {
+        for (dst, src) in dst.iter_mut().zip(src.iter().rev()) {
+            self.blend_function(dst, src);
+        }
+    }
+
+    fn blit_impl(&self, buffer: &mut [T], buffer_width: usize, src_rect: Rect, dst_rect: Rect, flip: Flip) {
         let span_length = (
             src_rect.x_range.end - src_rect.x_range.start
         ).min(
@@ -41,6 +59,9 @@ pub trait Blittable<T: Copy> : SizedSurface + BufferProvider<T> {
         ).min(
             dst_rect.y_range.end - dst_rect.y_range.start
         );
+        if span_length == 0 || span_count == 0 {
+            return;
+        }
         let width = self.get_width();
         let mut src_stride = src_rect.y_range.start * width + src_rect.x_range.start;
         let src_buffer = self.get_buffer();
@@ -52,55 +73,25 @@ pub trait Blittable<T: Copy> : SizedSurface + BufferProvider<T> {
             Flip::XY => (true, true)
         };
 
-        if flip_y {
-            let mut dst_stride = (dst_rect.y_range.start + span_count - 1) * buffer_width + dst_rect.x_range.start;
+        let first_dst_row = if flip_y {
+            dst_rect.y_range.start + span_count - 1
+        } else {
+            dst_rect.y_range.start
+        };
+        let mut dst_stride = first_dst_row * buffer_width + dst_rect.x_range.start;
+        for _ in 0..span_count {
+            let dst = &mut buffer[dst_stride..dst_stride+span_length];
+            let src = &src_buffer[src_stride..src_stride+span_length];
             if flip_x {
-                for _ in 0..span_count {
-                    let zipped = (&mut buffer[dst_stride..dst_stride+span_length])
-                        .iter_mut()
-                        .zip((&src_buffer[src_stride..src_stride+span_length]).iter().rev());
-                    for (dest, src) in zipped {
-                        self.blend_function(dest, src);
-                    }
-                    src_stride += width;
-                    dst_stride -= buffer_width;
-                }
+                self.blend_row_reversed(dst, src);
             } else {
-                for _ in 0..span_count {
-                    let zipped = (&mut buffer[dst_stride..dst_stride+span_length])
-                        .iter_mut()
-                        .zip(&src_buffer[src_stride..src_stride+span_length]);
-                    for (dest, src) in zipped {
-                        self.blend_function(dest, src);
-                    }
-                    src_stride += width;
-                    dst_stride -= buffer_width;
-                }
+                self.blend_row(dst, src);
             }
-        } else {
-            let mut dst_stride = dst_rect.y_range.start * buffer_width + dst_rect.x_range.start;
-            if flip_x {
-                for _ in 0..span_count {
-                    let zipped = (&mut buffer[dst_stride..dst_stride+span_length])
-                        .iter_mut()
-                        .zip((&src_buffer[src_stride..src_stride+span_length]).iter().rev());
-                    for (dest, src) in zipped {
-                        self.blend_function(dest, src);
-                    }
-                    src_stride += width;
-                    dst_stride += buffer_width;
-                }
+            src_stride += width;
+            if flip_y {
+                dst_stride = dst_stride.saturating_sub(buffer_width);
             } else {
-                for _ in 0..span_count {
-                    let zipped = (&mut buffer[dst_stride..dst_stride+span_length])
-                        .iter_mut()
-                        .zip(&src_buffer[src_stride..src_stride+span_length]);
-                    for (dest, src) in zipped {
-                        self.blend_function(dest, src);
-                    }
-                    src_stride += width;
-                    dst_stride += buffer_width;
-                }
+                dst_stride += buffer_width;
             }
         }
     }
@@ -114,7 +105,7 @@ pub enum Flip {
     XY
 }
 
-fn blit_ext<T: Copy, TBlittable: Blittable<T>>(
+fn blit_ext<T: Copy, TBlittable: Blittable<T> + ?Sized>(
     drawable: &TBlittable, buffer: &mut [T], buffer_width: usize,
     src_x: usize, src_y: usize,
     src_width: usize, src_height: usize,
@@ -159,28 +150,22 @@ fn blit_ext<T: Copy, TBlittable: Blittable<T>>(
         Flip::XY => (true, true)
     };
 
+    // visible part of a flipped source is mirrored within the source subrect,
+    // it may be clipped from both sides when the source is bigger than the destination
     if flip_x {
-        if src_x < src_rect.x_range.start {
-            let x_diff = src_width - src_rect.get_width();
-            src_rect.x_range.start -= x_diff;
-            src_rect.x_range.end -= x_diff;
-        } else if src_rect.get_width() > dst_rect.get_width() {
-            let x_diff = src_rect.get_width() - dst_rect.get_width();
-            src_rect.x_range.start += x_diff;
-            src_rect.x_range.end += x_diff;
-        }
+        let first = src_x.min(src_width_max);
+        let visible = src_rect.get_width().min(dst_rect.get_width());
+        let clipped_before = src_rect.x_range.start - first;
+        src_rect.x_range.start = src_width_max - (clipped_before + visible);
+        src_rect.x_range.end = src_rect.x_range.start + visible;
     }
 
     if flip_y {
-        if src_y < src_rect.y_range.start {
-            let y_diff = src_height - src_rect.get_height();
-            src_rect.y_range.start -= y_diff;
-            src_rect.y_range.end -= y_diff;
-        } else if src_rect.get_height() > dst_rect.get_height() {
-            let y_diff = src_rect.get_height() - dst_rect.get_height();
-            src_rect.y_range.start += y_diff;
-            src_rect.y_range.end += y_diff;
-        }
+        let first = src_y.min(src_height_max);
+        let visible = src_rect.get_height().min(dst_rect.get_height());
+        let clipped_before = src_rect.y_range.start - first;
+        src_rect.y_range.start = src_height_max - (clipped_before + visible);
+        src_rect.y_range.end = src_rect.y_range.start + visible;
     }
 
     drawable.blit_impl(
@@ -192,7 +177,7 @@ fn blit_ext<T: Copy, TBlittable: Blittable<T>>(
     )
 }
 
-pub struct BlitBuilder<'a, T: Copy, TBlittable: Blittable<T>> {
+pub struct BlitBuilder<'a, T: Copy, TBlittable: Blittable<T> + ?Sized> {
     drawable: &'a TBlittable,
     buffer: &'a mut [T],
     buffer_width: usize,
@@ -204,9 +189,11 @@ pub struct BlitBuilder<'a, T: Copy, TBlittable: Blittable<T>> {
     dst_y: i16,
     dst_width: usize,
     dst_height: usize,
-    flip: Flip
+    flip: Flip,
+    transform: Option<Transform>,
+    pivot: (f32, f32)
 }
-impl<'a, T: Copy, TBlittable: Blittable<T>> BlitBuilder<'a, T, TBlittable> {
+impl<'a, T: Copy, TBlittable: Blittable<T> + ?Sized> BlitBuilder<'a, T, TBlittable> {
     pub fn create_ext(buffer: &'a mut [T], buffer_width: usize, drawable: &'a TBlittable) -> Self {
         let dst_height = buffer.len() / buffer_width;
         Self {
@@ -221,7 +208,9 @@ impl<'a, T: Copy, TBlittable: Blittable<T>> BlitBuilder<'a, T, TBlittable> {
             dst_y: 0,
             dst_width: buffer_width,
             dst_height,
-            flip: Flip::None
+            flip: Flip::None,
+            transform: None,
+            pivot: (0.0, 0.0)
         }
     }
     pub fn create(
@@ -261,7 +250,28 @@ impl<'a, T: Copy, TBlittable: Blittable<T>> BlitBuilder<'a, T, TBlittable> {
             ..self
         }
     }
+    /// rotates and scales the source subrect around the pivot, which is then placed at
+    /// transform translation + dest pos. Transformed blits are clipped to the destination buffer
+    /// and ignore the dest subrect size. A flip mirrors the image within the source subrect
+    /// before the transform, so the pivot keeps pointing at the same spot of the subrect
+    pub fn with_transform(self, transform: Transform) -> Self {
+        Self {
+            transform: Some(transform),
+            ..self
+        }
+    }
+    /// pivot of a transformed blit relative to the top left corner of the source subrect
+    pub fn with_pivot(self, pivot_x: f32, pivot_y: f32) -> Self {
+        Self {
+            pivot: (pivot_x, pivot_y),
+            ..self
+        }
+    }
     pub fn blit(&mut self) {
+        if let Some(transform) = self.transform {
+            self.blit_transformed(transform);
+            return;
+        }
         blit_ext(
             self.drawable,
             self.buffer,
@@ -279,7 +289,90 @@ impl<'a, T: Copy, TBlittable: Blittable<T>> BlitBuilder<'a, T, TBlittable> {
     }
 }
 
-pub trait BlitDestination<'a, T:Copy, TBlittable: Blittable<T>> : BufferProviderMut<T> + SizedSurface {
+impl<'a, T: Copy, TBlittable: Blittable<T> + ?Sized> BlitBuilder<'a, T, TBlittable> {
+    fn blit_transformed(&mut self, transform: Transform) {
+        let src_x_max = (self.src_x + self.src_width).min(self.drawable.get_width());
+        let src_y_max = (self.src_y + self.src_height).min(self.drawable.get_height());
+        if self.src_x >= src_x_max || self.src_y >= src_y_max || self.buffer_width == 0 {
+            return;
+        }
+        let buffer_height = self.buffer.len() / self.buffer_width;
+
+        // source space is relative to the pivot, so the pivot ends up at the translation
+        let (u_min, u_max) = (-self.pivot.0, (src_x_max - self.src_x) as f32 - self.pivot.0);
+        let (v_min, v_max) = (-self.pivot.1, (src_y_max - self.src_y) as f32 - self.pivot.1);
+
+        let matrix = Mat3A::from_translation(vec2(self.dst_x as f32, self.dst_y as f32)) * transform.matrix;
+        if matrix.determinant().abs() < f32::EPSILON {
+            return;
+        }
+        let inverse = matrix.inverse();
+
+        let corners = [(u_min, v_min), (u_max, v_min), (u_max, v_max), (u_min, v_max)]
+            .map(|(u, v)| matrix * vec3a(u, v, 1.0));
+        let x_min = corners.iter().map(|it| it.x).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let y_min = corners.iter().map(|it| it.y).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let x_max = (corners.iter().map(|it| it.x).fold(f32::MIN, f32::max).ceil().max(0.0) as usize)
+            .min(self.buffer_width);
+        let y_max = (corners.iter().map(|it| it.y).fold(f32::MIN, f32::max).ceil().max(0.0) as usize)
+            .min(buffer_height);
+        if x_min >= x_max || y_min >= y_max {
+            return;
+        }
+
+        // moving one pixel to the right in the destination moves by (du, dv) in the source
+        let (du, dv) = (inverse.x_axis.x, inverse.x_axis.y);
+        let src_buffer = self.drawable.get_buffer();
+        let src_width = self.drawable.get_width();
+        let (flip_x, flip_y) = match self.flip {
+            Flip::None => (false, false),
+            Flip::X => (true, false),
+            Flip::Y => (false, true),
+            Flip::XY => (true, true)
+        };
+        let (span_u, span_v) = (src_x_max - self.src_x - 1, src_y_max - self.src_y - 1);
+
+        for y in y_min..y_max {
+            let row_start = inverse * vec3a(x_min as f32 + 0.5, y as f32 + 0.5, 1.0);
+
+            // find the part of the row which maps inside of the source rect
+            let mut t_start = 0.0f32;
+            let mut t_end = (x_max - x_min) as f32;
+            for (start, delta, min, max) in [(row_start.x, du, u_min, u_max), (row_start.y, dv, v_min, v_max)] {
+                if delta.abs() < f32::EPSILON {
+                    if start < min || start >= max {
+                        t_end = t_start;
+                    }
+                } else {
+                    let t0 = (min - start) / delta;
+                    let t1 = (max - start) / delta;
+                    t_start = t_start.max(t0.min(t1));
+                    t_end = t_end.min(t0.max(t1));
+                }
+            }
+            if t_end <= t_start {
+                continue;
+            }
+            let span_start = t_start.ceil() as usize;
+            let span_end = (t_end.ceil() as usize).min(x_max - x_min);
+
+            let dst_stride = y * self.buffer_width + x_min;
+            let mut u = row_start.x + du * span_start as f32;
+            let mut v = row_start.y + dv * span_start as f32;
+            for dest in self.buffer[dst_stride + span_start..dst_stride + span_end].iter_mut() {
+                let offset_u = ((u - u_min).max(0.0) as usize).min(span_u);
+                let offset_v = ((v - v_min).max(0.0) as usize).min(span_v);
+                let src_u = self.src_x + if flip_x { span_u - offset_u } else { offset_u };
+                let src_v = self.src_y + if flip_y { span_v - offset_v } else { offset_v };
+                self.drawable.blend_function(dest, &src_buffer[src_v * src_width + src_u]);
+                u += du;
+                v += dv;
+            }
+        }
+    }
+}
+
+pub trait BlitDestination<'a, T:Copy, TBlittable: Blittable<T> + ?Sized> : BufferProviderMut<T> + SizedSurface {
     fn initiate_blit_on_self(&'a mut self, source_blittable: &'a TBlittable) -> BlitBuilder<'a, T, TBlittable> {
         let width = self.get_width();
         BlitBuilder::create_ext(
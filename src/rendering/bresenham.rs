@@ -38,7 +38,7 @@ fn plot_bresenham_circle(
     }
 }
 
-fn plot_bresenham_line<F : FnMut(i16, i16) -> ()>(x0: i16, y0: i16, x1: i16, y1: i16, mut plot_func: F) {
+pub(crate) fn plot_bresenham_line<F : FnMut(i16, i16) -> ()>(x0: i16, y0: i16, x1: i16, y1: i16, mut plot_func: F) {
     if y0 == y1 {
         for x in x0.min(x1)..=x0.max(x1) { plot_func(x, y0); }
     } else if x0 == x1 {
@@ -133,6 +133,7 @@ impl<'a, T: Copy> BresenhamCircleDrawer<'a, T> {
 pub struct LineStripRasterizer<'a, T: Copy + Default>  {
     buffer: &'a mut [T],
     buffer_width: usize,
+    band_top: usize,
     transform: Transform,
     color: T,
     closed: bool
@@ -141,9 +142,15 @@ impl<'a, T: Copy + Default> LineStripRasterizer<'a, T> {
     pub fn create(buffer_provider: &'a mut (impl BufferProviderMut<T>+SizedSurface)) -> Self {
         let buffer_width = buffer_provider.get_width();
         let buffer = buffer_provider.get_buffer_mut();
+        Self::create_band(buffer, buffer_width, 0)
+    }
+
+    /// draws into the rows of a band starting at band_top, everything else is skipped
+    pub(crate) fn create_band(buffer: &'a mut [T], buffer_width: usize, band_top: usize) -> Self {
         Self {
             buffer,
             buffer_width,
+            band_top,
             transform: Transform::from_identity(),
             color: Default::default(),
             closed: false
@@ -212,6 +219,7 @@ impl<'a, T: Copy + Default> LineStripRasterizer<'a, T> {
                     ]
                 );
                 LineRasterizer::create_from_raw(self.buffer, self.buffer_width)
+                    .with_band_top(self.band_top)
                     .from(next[0])
                     .to(next[1])
                     .rasterize(self.color);
@@ -225,6 +233,7 @@ impl<'a, T: Copy + Default> LineStripRasterizer<'a, T> {
                     ]
                 );
                 LineRasterizer::create_from_raw(self.buffer, self.buffer_width)
+                    .with_band_top(self.band_top)
                     .from(next[0])
                     .to(next[1])
                     .rasterize(self.color);
@@ -237,6 +246,7 @@ impl<'a, T: Copy + Default> LineStripRasterizer<'a, T> {
 pub struct LineRasterizer<'a, T: Copy> {
     buffer: &'a mut [T],
     buffer_width: usize,
+    band_top: usize,
     from: (i16, i16),
     to: (i16, i16)
 }
@@ -246,6 +256,7 @@ impl<'a, T: Copy> LineRasterizer<'a, T> {
         Self {
             buffer,
             buffer_width,
+            band_top: 0,
             from: (0, 0),
             to: (0, 0)
         }
@@ -257,11 +268,17 @@ impl<'a, T: Copy> LineRasterizer<'a, T> {
         Self {
             buffer,
             buffer_width,
+            band_top: 0,
             from: (0, 0),
             to: (0, 0)
         }
     }
 
+    /// the buffer is a band of a bigger surface starting at band_top
+    pub(crate) fn with_band_top(self, band_top: usize) -> Self {
+        Self { band_top, ..self }
+    }
+
     pub fn from(self, from: (i16, i16)) -> Self {
         Self { from, ..self }
     }
@@ -278,8 +295,9 @@ impl<'a, T: Copy> LineRasterizer<'a, T> {
             self.to.0,
             self.to.1,
             |x, y| {
+                let y = y as isize - self.band_top as isize;
                 if (0..self.buffer_width as i16).contains(&x) &&
-                    (0..buffer_height as i16).contains(&y)
+                    (0..buffer_height as isize).contains(&y)
                 {
                     self.buffer[x as usize + y as usize * self.buffer_width] = color;
                 }
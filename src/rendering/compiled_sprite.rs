@@ -0,0 +1,201 @@
+use crate::rendering::blittable::{Blittable, BufferProvider, Flip, Rect, SizedSurface};
+use crate::rendering::BlittableSurface;
+
+/// A run-length encoded sprite. Every row is a list of runs of transparent pixels to skip
+/// and opaque pixels to copy, so blits never look at the color key and never touch
+/// transparent parts of the destination. A mirrored copy of the runs is kept for flips by x.
+/// Blit it with BlitBuilder as any other Blittable.
+///
+/// It pays off for big sprites with large transparent areas, e.g. outlines, rings or sparse effects.
+/// Small or mostly opaque sprites and sprites with many short runs are faster with a ColorKeyWrapper,
+/// see benches/sprite_blit.rs
+pub struct CompiledSprite {
+    width: u16,
+    height: u16,
+    color_key: u8,
+    /// the whole image, used by transformed blits
+    buffer: Vec<u8>,
+    runs: RunTable,
+    mirrored_runs: RunTable
+}
+
+struct RunTable {
+    /// index of the first run and of the first packed pixel of every row, plus one entry past the last row
+    rows: Vec<(u32, u32)>,
+    /// (transparent pixels to skip, opaque pixels to copy)
+    runs: Vec<(u16, u16)>,
+    /// opaque pixels of all runs, one after another
+    pixels: Vec<u8>
+}
+
+impl RunTable {
+    fn new(buffer: &[u8], width: usize, color_key: u8, mirrored: bool) -> Self {
+        let mut rows = Vec::with_capacity(buffer.len() / width.max(1) + 1);
+        let mut runs = Vec::new();
+        let mut pixels = Vec::new();
+        let mut row_pixels = Vec::with_capacity(width);
+        if width > 0 {
+            for row in buffer.chunks_exact(width) {
+                rows.push((runs.len() as u32, pixels.len() as u32));
+                row_pixels.clear();
+                row_pixels.extend_from_slice(row);
+                if mirrored {
+                    row_pixels.reverse();
+                }
+                let mut x = 0;
+                let mut run_end = 0;
+                while x < width {
+                    if row_pixels[x] == color_key {
+                        x += 1;
+                        continue;
+                    }
+                    let start = x;
+                    while x < width && row_pixels[x] != color_key {
+                        x += 1;
+                    }
+                    runs.push(((start - run_end) as u16, (x - start) as u16));
+                    pixels.extend_from_slice(&row_pixels[start..x]);
+                    run_end = x;
+                }
+            }
+        }
+        rows.push((runs.len() as u32, pixels.len() as u32));
+        Self { rows, runs, pixels }
+    }
+
+    /// dst covers source columns from clip_start on
+    #[inline(always)]
+    fn copy_row(&self, y: usize, dst: &mut [u8], clip_start: usize, clipped: bool) {
+        let (first_run, first_pixel) = self.rows[y];
+        let runs = &self.runs[first_run as usize..self.rows[y + 1].0 as usize];
+        let mut pixels = &self.pixels[first_pixel as usize..];
+        let mut x = 0;
+        if !clipped {
+            for &(skip, len) in runs.iter() {
+                let (skip, len) = (skip as usize, len as usize);
+                x += skip;
+                copy_run(&mut dst[x..x + len], &pixels[..len]);
+                pixels = &pixels[len..];
+                x += len;
+            }
+            return;
+        }
+        let clip_end = clip_start + dst.len();
+        for &(skip, len) in runs.iter() {
+            let (skip, len) = (skip as usize, len as usize);
+            let run_start = x + skip;
+            x = run_start + len;
+            let run_pixels = &pixels[..len];
+            pixels = &pixels[len..];
+            if x <= clip_start {
+                continue;
+            }
+            if run_start >= clip_end {
+                break;
+            }
+            let (start, end) = (run_start.max(clip_start), x.min(clip_end));
+            copy_run(&mut dst[start - clip_start..end - clip_start], &run_pixels[start - run_start..end - run_start]);
+        }
+    }
+}
+
+/// Runs are mostly short, and a call to memcpy costs more than a couple of overlapping moves
+#[inline(always)]
+fn copy_run(dst: &mut [u8], src: &[u8]) {
+    let len = src.len();
+    if len >= 32 {
+        dst.copy_from_slice(src);
+    } else if len >= 16 {
+        dst[..16].copy_from_slice(&src[..16]);
+        dst[len - 16..].copy_from_slice(&src[len - 16..]);
+    } else if len >= 8 {
+        dst[..8].copy_from_slice(&src[..8]);
+        dst[len - 8..].copy_from_slice(&src[len - 8..]);
+    } else if len >= 4 {
+        dst[..4].copy_from_slice(&src[..4]);
+        dst[len - 4..].copy_from_slice(&src[len - 4..]);
+    } else {
+        for (dst, src) in dst.iter_mut().zip(src.iter()) {
+            *dst = *src;
+        }
+    }
+}
+
+impl CompiledSprite {
+    pub fn from_surface(surface: &BlittableSurface, color_key: u8) -> Self {
+        let (width, height) = (surface.get_width(), surface.get_height());
+        let buffer = surface.get_buffer().to_vec();
+        Self {
+            width: width as u16,
+            height: height as u16,
+            color_key,
+            runs: RunTable::new(&buffer, width, color_key, false),
+            mirrored_runs: RunTable::new(&buffer, width, color_key, true),
+            buffer
+        }
+    }
+
+    pub fn get_color_key(&self) -> u8 {
+        self.color_key
+    }
+
+    /// count of opaque runs, handy to estimate the blit cost
+    pub fn get_span_count(&self) -> usize {
+        self.runs.runs.len()
+    }
+}
+
+impl SizedSurface for CompiledSprite {
+    fn get_width(&self) -> usize { self.width as _ }
+
+    fn get_height(&self) -> usize { self.height as _ }
+}
+
+impl BufferProvider<u8> for CompiledSprite {
+    fn get_buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl Blittable<u8> for CompiledSprite {
+    /// used by transformed blits only
+    #[inline(always)]
+    fn blend_function(&self, dst: &mut u8, src: &u8) {
+        if *src != self.color_key {
+            *dst = *src;
+        }
+    }
+
+    fn blit_impl(&self, buffer: &mut [u8], buffer_width: usize, self_rect: Rect, dst_rect: Rect, flip: Flip) {
+        let span_length = self_rect.get_width().min(dst_rect.get_width());
+        let span_count = self_rect.get_height().min(dst_rect.get_height());
+        if span_length == 0 || span_count == 0 {
+            return;
+        }
+        let (flip_x, flip_y) = match flip {
+            Flip::None => (false, false),
+            Flip::X => (true, false),
+            Flip::Y => (false, true),
+            Flip::XY => (true, true)
+        };
+        // mirrored runs are read left to right as well, only the clip rect is mirrored
+        let (runs, clip_start) = if flip_x {
+            (&self.mirrored_runs, self.get_width() - self_rect.x_range.start - span_length)
+        } else {
+            (&self.runs, self_rect.x_range.start)
+        };
+        let clipped = span_length != self.get_width();
+
+        // only rows inside of the clipped rect are visited
+        for row in 0..span_count {
+            let src_y = self_rect.y_range.start + row;
+            let dst_y = if flip_y {
+                dst_rect.y_range.start + span_count - 1 - row
+            } else {
+                dst_rect.y_range.start + row
+            };
+            let dst_start = dst_y * buffer_width + dst_rect.x_range.start;
+            runs.copy_row(src_y, &mut buffer[dst_start..dst_start + span_length], clip_start, clipped);
+        }
+    }
+}
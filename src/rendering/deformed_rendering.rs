@@ -17,17 +17,26 @@ pub struct TriangleRasterizer<'a, T: Copy> {
     buffer: &'a mut [T],
     buffer_width: usize,
     buffer_height: usize,
+    /// y of the first buffer row, it isn't zero when the buffer is a band of a bigger surface
+    band_top: usize,
     transform: Transform
 }
 impl<'a, T: Copy> TriangleRasterizer<'a, T> {
     pub fn create(buffer_provider: &'a mut (impl BufferProviderMut<T>+SizedSurface)) -> Self {
         let buffer_width = buffer_provider.get_width();
         let buffer = buffer_provider.get_buffer_mut();
+        Self::create_band(buffer, buffer_width, 0)
+    }
+
+    /// rasterizes into the rows of a band starting at band_top, everything else is skipped.
+    /// Triangles are walked the same way as for the whole surface, so bands match it pixel to pixel
+    pub(crate) fn create_band(buffer: &'a mut [T], buffer_width: usize, band_top: usize) -> Self {
         let buffer_height = buffer.len() / buffer_width;
         Self {
             buffer,
             buffer_width,
             buffer_height,
+            band_top,
             transform: Transform::from_identity()
         }
     }
@@ -103,6 +112,10 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
                 positions
             };
 
+            if !self.intersects_band(top_pos.1, bottom_pos.1) {
+                continue;
+            }
+
             if top_pos.1 as i16 == middle_pos.1 as i16 {
                 self.draw_flat_top_colored(
                     color,
@@ -138,6 +151,10 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
         }
     }
 
+    fn intersects_band(&self, top_y: f32, bottom_y: f32) -> bool {
+        bottom_y.ceil() > self.band_top as f32 && top_y.ceil() < (self.band_top + self.buffer_height) as f32
+    }
+
     fn draw_flat_bottom_colored(&mut self, color: T, top_pos: (f32, f32), middle_pos: (f32, f32), bottom_pos: (f32, f32)) {
         let [left_pos, middle_pos, right_pos] = {
             if bottom_pos.0 <= middle_pos.0 {
@@ -218,7 +235,8 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
         if x0 > x1 {
             return;
         }
-        if (0..(self.buffer_height) as i16).contains(&y) {
+        let y = y as isize - self.band_top as isize;
+        if (0..self.buffer_height as isize).contains(&y) {
             let stride = y as usize * self.buffer_width;
 
             let xl = x0.max(0.0) as usize;
@@ -235,7 +253,7 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
 
     pub fn rasterize_with_surface(
         self,
-        drawable: &'a impl Blittable<T>,
+        drawable: &'a (impl Blittable<T> + ?Sized),
         vertices: &[TexturedVertex],
         indices: &[u16]
     ) {
@@ -261,7 +279,7 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
 
     pub fn rasterize_with_surface_iter(
         mut self,
-        triangles: impl IntoIterator<Item=([TexturedVertex; 3], &'a (impl Blittable<T> + 'a))>
+        triangles: impl IntoIterator<Item=([TexturedVertex; 3], &'a (impl Blittable<T> + ?Sized + 'a))>
     ) {
         for (triangle, drawable) in triangles.into_iter() {
             let mut positions = triangle.map(|it| it.position);
@@ -286,6 +304,10 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
                 )
             };
 
+            if !self.intersects_band(top_pos.1, bottom_pos.1) {
+                continue;
+            }
+
             if top_pos.1 as i16 == middle_pos.1 as i16 {
                 self.draw_flat_top_with_surface(
                     drawable,
@@ -329,7 +351,7 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
 
     fn draw_flat_bottom_with_surface(
         &mut self,
-        drawable: &'a impl Blittable<T>,
+        drawable: &'a (impl Blittable<T> + ?Sized),
         top_pos: (f32, f32),
         middle_pos: (f32, f32),
         bottom_pos: (f32, f32),
@@ -387,7 +409,7 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
     }
     fn draw_flat_top_with_surface(
         &mut self,
-        drawable: &'a impl Blittable<T>,
+        drawable: &'a (impl Blittable<T> + ?Sized),
         top_pos: (f32, f32),
         middle_pos: (f32, f32),
         bottom_pos: (f32, f32),
@@ -447,7 +469,7 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
 
     fn draw_span(
         &mut self,
-        drawable: &impl Blittable<T>,
+        drawable: &(impl Blittable<T> + ?Sized),
         interpolator_0: Vec3A,
         interpolator_1: Vec3A,
         y: i16
@@ -459,7 +481,8 @@ impl<'a, T: Copy> TriangleRasterizer<'a, T> {
             return;
         }
 
-        if (0..(self.buffer_height) as i16).contains(&y) {
+        let y = y as isize - self.band_top as isize;
+        if (0..self.buffer_height as isize).contains(&y) {
             let stride = y as usize * self.buffer_width;
             let corr = x0 - interpolator_0.x;
 
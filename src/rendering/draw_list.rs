@@ -0,0 +1,236 @@
+use bumpalo::Bump;
+use crate::rendering::blittable::{BlitBuilder, Blittable, BufferProviderMut, Flip, SizedSurface};
+use crate::rendering::bresenham::{LineRasterizer, LineStripRasterizer};
+use crate::rendering::deformed_rendering::{TexturedVertex, TriangleRasterizer, Vertex};
+use crate::rendering::fonts::rich_text::RichTextFont;
+use crate::rendering::transform::Transform;
+
+/// Commands are drawn by layer first, then by y, so things lower on the screen overlap
+/// things above them. Commands with equal keys are drawn in the order they were pushed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SortKey {
+    pub layer: i16,
+    pub y: i16
+}
+
+impl SortKey {
+    pub fn new(layer: i16, y: i16) -> Self {
+        Self { layer, y }
+    }
+}
+
+enum DrawCommand<'a> {
+    Blit {
+        drawable: &'a dyn Blittable<u8>,
+        position: (i16, i16),
+        source_subrect: Option<(usize, usize, usize, usize)>,
+        flip: Flip
+    },
+    Triangles {
+        color: u8,
+        vertices: &'a [Vertex],
+        indices: &'a [u16],
+        transform: Transform
+    },
+    TexturedTriangles {
+        drawable: &'a dyn Blittable<u8>,
+        vertices: &'a [TexturedVertex],
+        indices: &'a [u16],
+        transform: Transform
+    },
+    Line {
+        color: u8,
+        from: (i16, i16),
+        to: (i16, i16)
+    },
+    LineStrip {
+        color: u8,
+        positions: &'a [(i16, i16)],
+        closed: bool,
+        transform: Transform
+    },
+    Text {
+        font: &'a dyn RichTextFont,
+        position: (i16, i16),
+        text: &'a str,
+        color_tint_idx: Option<u8>
+    }
+}
+
+/// Records draw commands with sort keys and draws them sorted in one pass.
+/// Commands, vertices and texts are copied into the arena, so it should outlive the list.
+/// Reset the arena between frames to reuse its memory:
+/// ```ignore
+/// arena.reset();
+/// let mut draw_list = DrawList::new(&arena);
+/// draw_list.push_blit(SortKey::new(0, y), &sprite, (x, y), Flip::None);
+/// draw_list.flush(ctx);
+/// ```
+pub struct DrawList<'a> {
+    arena: &'a Bump,
+    commands: bumpalo::collections::Vec<'a, (SortKey, DrawCommand<'a>)>
+}
+
+impl<'a> DrawList<'a> {
+    pub fn new(arena: &'a Bump) -> Self {
+        Self {
+            arena,
+            commands: bumpalo::collections::Vec::new_in(arena)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn push_blit(&mut self, key: SortKey, drawable: &'a dyn Blittable<u8>, position: (i16, i16), flip: Flip) {
+        self.commands.push((key, DrawCommand::Blit { drawable, position, source_subrect: None, flip }));
+    }
+
+    /// source_subrect is (x, y, width, height)
+    pub fn push_blit_subrect(
+        &mut self,
+        key: SortKey,
+        drawable: &'a dyn Blittable<u8>,
+        position: (i16, i16),
+        source_subrect: (usize, usize, usize, usize),
+        flip: Flip
+    ) {
+        self.commands.push((key, DrawCommand::Blit { drawable, position, source_subrect: Some(source_subrect), flip }));
+    }
+
+    pub fn push_triangles(&mut self, key: SortKey, color: u8, vertices: &[Vertex], indices: &[u16], transform: Transform) {
+        let vertices = self.arena.alloc_slice_copy(vertices);
+        let indices = self.arena.alloc_slice_copy(indices);
+        self.commands.push((key, DrawCommand::Triangles { color, vertices, indices, transform }));
+    }
+
+    pub fn push_textured_triangles(
+        &mut self,
+        key: SortKey,
+        drawable: &'a dyn Blittable<u8>,
+        vertices: &[TexturedVertex],
+        indices: &[u16],
+        transform: Transform
+    ) {
+        let vertices = self.arena.alloc_slice_copy(vertices);
+        let indices = self.arena.alloc_slice_copy(indices);
+        self.commands.push((key, DrawCommand::TexturedTriangles { drawable, vertices, indices, transform }));
+    }
+
+    pub fn push_line(&mut self, key: SortKey, color: u8, from: (i16, i16), to: (i16, i16)) {
+        self.commands.push((key, DrawCommand::Line { color, from, to }));
+    }
+
+    pub fn push_line_strip(&mut self, key: SortKey, color: u8, positions: &[(i16, i16)], closed: bool, transform: Transform) {
+        let positions = self.arena.alloc_slice_copy(positions);
+        self.commands.push((key, DrawCommand::LineStrip { color, positions, closed, transform }));
+    }
+
+    /// plain text without markup, new lines are respected
+    pub fn push_text(
+        &mut self,
+        key: SortKey,
+        font: &'a dyn RichTextFont,
+        position: (i16, i16),
+        text: &str,
+        color_tint_idx: Option<u8>
+    ) {
+        let text = self.arena.alloc_str(text);
+        self.commands.push((key, DrawCommand::Text { font, position, text, color_tint_idx }));
+    }
+
+    /// sorts and draws all commands, then clears the list
+    pub fn flush(&mut self, dest: &mut (impl BufferProviderMut<u8> + SizedSurface)) {
+        let width = dest.get_width();
+        if width == 0 {
+            self.commands.clear();
+            return;
+        }
+        let buffer = dest.get_buffer_mut();
+
+        // the sort is stable, so equal keys keep the push order
+        self.commands.sort_by_key(|(key, _)| *key);
+
+        for (_, command) in self.commands.drain(..) {
+            match command {
+                DrawCommand::Blit { drawable, position, source_subrect, flip } => {
+                    let mut builder = BlitBuilder::create_ext(&mut *buffer, width, drawable)
+                        .with_dest_pos(position.0, position.1)
+                        .with_flip(flip);
+                    if let Some((x, y, w, h)) = source_subrect {
+                        builder = builder.with_source_subrect(x, y, w, h);
+                    }
+                    builder.blit();
+                },
+                DrawCommand::Triangles { color, vertices, indices, transform } => {
+                    TriangleRasterizer::create_band(&mut *buffer, width, 0)
+                        .with_transform(transform)
+                        .rasterize_with_color(color, vertices, indices);
+                },
+                DrawCommand::TexturedTriangles { drawable, vertices, indices, transform } => {
+                    TriangleRasterizer::create_band(&mut *buffer, width, 0)
+                        .with_transform(transform)
+                        .rasterize_with_surface(drawable, vertices, indices);
+                },
+                DrawCommand::Line { color, from, to } => {
+                    LineRasterizer::create_from_raw(&mut *buffer, width)
+                        .from(from)
+                        .to(to)
+                        .rasterize(color);
+                },
+                DrawCommand::LineStrip { color, positions, closed, transform } => {
+                    let rasterizer = LineStripRasterizer::create_band(&mut *buffer, width, 0)
+                        .with_color(color)
+                        .with_transform(transform);
+                    if closed {
+                        rasterizer.closed().rasterize_slice(positions);
+                    } else {
+                        rasterizer.rasterize_slice(positions);
+                    }
+                },
+                DrawCommand::Text { font, position, text, color_tint_idx } => {
+                    draw_plain_text(buffer, width, font, position, text, color_tint_idx);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn draw_plain_text(
+    buffer: &mut [u8],
+    buffer_width: usize,
+    font: &dyn RichTextFont,
+    position: (i16, i16),
+    text: &str,
+    color_tint_idx: Option<u8>
+) {
+    let (mut x, mut y) = position;
+    let mut previous = None;
+    for chr in text.chars() {
+        match chr {
+            '\n' => {
+                x = position.0;
+                y += font.line_height() as i16;
+                previous = None;
+                continue;
+            },
+            ' ' => {
+                x += font.space_width() as i16;
+                previous = None;
+                continue;
+            },
+            _ => {}
+        }
+        if let Some(previous) = previous {
+            x += font.kerning(previous, chr);
+        }
+        font.draw_char(buffer, buffer_width, x, y, chr, color_tint_idx);
+        x += font.char_advance(chr);
+        previous = Some(chr);
+    }
+}
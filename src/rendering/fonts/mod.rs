@@ -1,2 +1,4 @@
 pub mod tri_spaced;
-pub mod font_align;
\ No newline at end of file
+pub mod font_align;
+pub mod proportional;
+pub mod rich_text;
\ No newline at end of file
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use crate::rendering::blittable::{BlitBuilder, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+use crate::rendering::fonts::font_align::{HorizontalAlignment, VerticalAlignment};
+use crate::rendering::fonts::rich_text::RichTextFont;
+use crate::rendering::fonts::tri_spaced::TextDrawer;
+
+#[derive(Copy, Clone)]
+pub struct ProportionalGlyph {
+    /// index of a page surface the glyph is located on
+    pub page: usize,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// offset of the glyph image relative to the pen position,
+    /// where pen y is the top of a line
+    pub x_offset: i16,
+    pub y_offset: i16,
+    /// how far the pen moves after drawing the glyph
+    pub x_advance: i16
+}
+
+/// A font with glyphs of arbitrary size and kerning pairs,
+/// e.g. loaded with format_loaders::bm_font or format_loaders::psf
+pub struct ProportionalFont {
+    line_height: usize,
+    base_line: usize,
+    glyphs: HashMap<char, ProportionalGlyph>,
+    kerning: HashMap<(char, char), i16>,
+    default_char: Option<char>,
+    pages: Vec<BlittableSurface>,
+    color_key: u8,
+    arena: bumpalo::Bump
+}
+
+impl ProportionalFont {
+    pub fn new(
+        line_height: usize,
+        base_line: usize,
+        glyphs: HashMap<char, ProportionalGlyph>,
+        kerning: HashMap<(char, char), i16>,
+        pages: Vec<BlittableSurface>
+    ) -> Self {
+        let default_char = ['?', ' ']
+            .iter()
+            .copied()
+            .find(|it| glyphs.contains_key(it));
+        Self {
+            line_height,
+            base_line,
+            glyphs,
+            kerning,
+            default_char,
+            pages,
+            color_key: 0,
+            arena: bumpalo::Bump::new()
+        }
+    }
+
+    /// palette index treated as transparent on page surfaces. It is 0 by default
+    pub fn with_color_key(self, color_key: u8) -> Self {
+        Self { color_key, ..self }
+    }
+
+    /// glyph which is drawn for characters missing in the font. It is '?' by default
+    pub fn with_default_char(self, default_char: char) -> Self {
+        Self { default_char: Some(default_char), ..self }
+    }
+
+    pub fn line_height(&self) -> usize {
+        self.line_height
+    }
+
+    pub fn base_line(&self) -> usize {
+        self.base_line
+    }
+
+    pub fn get_glyph(&self, chr: char) -> Option<&ProportionalGlyph> {
+        self.glyphs
+            .get(&chr)
+            .or_else(|| self.default_char.and_then(|it| self.glyphs.get(&it)))
+    }
+
+    pub fn get_kerning(&self, first: char, second: char) -> i16 {
+        self.kerning
+            .get(&(first, second))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn space_width(&self) -> usize {
+        self.glyphs
+            .get(&' ')
+            .map(|it| it.x_advance.max(0) as usize)
+            .unwrap_or(self.line_height / 3)
+    }
+
+    /// measures a single line, kerning included
+    pub fn measure_text_width(&self, s: &str) -> usize {
+        let mut width = 0i32;
+        let mut previous = None;
+        for chr in s.chars() {
+            if let Some(prev) = previous {
+                width += self.get_kerning(prev, chr) as i32;
+            }
+            width += match chr {
+                ' ' => self.space_width() as i32,
+                _ => self.get_glyph(chr).map(|it| it.x_advance as i32).unwrap_or(0)
+            };
+            previous = Some(chr);
+        }
+        width.max(0) as usize
+    }
+
+    pub(crate) fn draw_glyph(
+        &self,
+        buffer: &mut [u8], width: usize,
+        pen_x: i16, pen_y: i16,
+        glyph: &ProportionalGlyph,
+        color_tint_idx: Option<u8>
+    ) {
+        if glyph.width == 0 || glyph.height == 0 {
+            return;
+        }
+        let page = match self.pages.get(glyph.page) {
+            None => return,
+            Some(page) => page
+        };
+        let (x, y) = (pen_x + glyph.x_offset, pen_y + glyph.y_offset);
+        match color_tint_idx {
+            None => {
+                BlitBuilder::create_ext(buffer, width, &page.with_color_key(self.color_key))
+                    .with_dest_pos(x, y)
+                    .with_source_subrect(glyph.x, glyph.y, glyph.width, glyph.height)
+                    .blit();
+            },
+            Some(idx) => {
+                BlitBuilder::create_ext(buffer, width, &page.with_color_key_blink(self.color_key, idx))
+                    .with_dest_pos(x, y)
+                    .with_source_subrect(glyph.x, glyph.y, glyph.width, glyph.height)
+                    .blit();
+            }
+        }
+    }
+}
+
+impl RichTextFont for ProportionalFont {
+    fn line_height(&self) -> usize {
+        self.line_height
+    }
+
+    fn space_width(&self) -> usize {
+        ProportionalFont::space_width(self)
+    }
+
+    fn char_advance(&self, chr: char) -> i16 {
+        self.get_glyph(chr).map(|it| it.x_advance).unwrap_or(0)
+    }
+
+    fn kerning(&self, first: char, second: char) -> i16 {
+        self.get_kerning(first, second)
+    }
+
+    fn draw_char(&self, buffer: &mut [u8], buffer_width: usize, x: i16, y: i16, chr: char, color_tint_idx: Option<u8>) {
+        if let Some(glyph) = self.get_glyph(chr) {
+            self.draw_glyph(buffer, buffer_width, x, y, glyph, color_tint_idx);
+        }
+    }
+}
+
+impl<Destination: BufferProviderMut<u8> + SizedSurface> TextDrawer<Destination> for ProportionalFont {
+    fn draw_text(&self, destination: &mut Destination, x: i16, y: i16, text: &str, color_tint_idx: Option<u8>) {
+        let mut current_x = x;
+        let mut current_y = y;
+        let mut previous = None;
+        for c in text.chars() {
+            if c == '\n' {
+                current_x = x;
+                current_y += self.line_height as i16;
+                previous = None;
+                continue;
+            }
+            if let Some(prev) = previous {
+                current_x += self.get_kerning(prev, c);
+            }
+            previous = Some(c);
+            if c == ' ' {
+                current_x += self.space_width() as i16;
+                continue;
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+            if let Some(glyph) = self.get_glyph(c) {
+                let width = destination.get_width();
+                self.draw_glyph(destination.get_buffer_mut(), width, current_x, current_y, glyph, color_tint_idx);
+                current_x += glyph.x_advance;
+            }
+        }
+    }
+
+    fn draw_text_in_box(
+        &self, destination: &mut Destination,
+        x: i16, y: i16,
+        box_width: usize, box_height: usize,
+        horizontal_alignment: HorizontalAlignment,
+        vertical_alignment: VerticalAlignment,
+        text: &str,
+        color_tint_idx: Option<u8>
+    ) {
+        struct LineInfo {
+            word_count: usize,
+            empty_space: i16
+        }
+
+        let space_width = self.space_width();
+        let mut line_info_vec = bumpalo::collections::Vec::new_in(&self.arena);
+
+        for line in text.lines() {
+            let mut current_x = 0;
+            let mut current_words = 0;
+            for word in line.split_ascii_whitespace() {
+                let new_width = self.measure_text_width(word);
+
+                let next_x = if current_words == 0 {
+                    new_width
+                } else {
+                    current_x + space_width + new_width
+                };
+
+                if next_x > box_width && current_words > 0 {
+                    line_info_vec.push(LineInfo {
+                        word_count: current_words,
+                        empty_space: box_width as i16 - current_x as i16
+                    });
+                    current_x = new_width;
+                    current_words = 1;
+                    continue;
+                }
+                current_x = next_x;
+                current_words += 1;
+            }
+            line_info_vec.push(LineInfo {
+                word_count: current_words,
+                empty_space: box_width as i16 - current_x as i16
+            });
+        }
+
+        let result_height = (self.line_height * line_info_vec.len()) as i16;
+        let mut current_y = y + match vertical_alignment {
+            VerticalAlignment::Top => 0,
+            VerticalAlignment::Center => (box_height as i16 - result_height) / 2,
+            VerticalAlignment::Bottom => box_height as i16 - result_height
+        };
+
+        let mut words = text.split_ascii_whitespace();
+        for LineInfo { word_count, empty_space } in line_info_vec.iter() {
+            let mut current_x = x + match horizontal_alignment {
+                HorizontalAlignment::Left => 0,
+                HorizontalAlignment::Center => *empty_space / 2,
+                HorizontalAlignment::Right => *empty_space
+            };
+            for i in 0..*word_count {
+                if let Some(word) = words.next() {
+                    if i != 0 {
+                        current_x += space_width as i16;
+                    }
+                    self.draw_text(destination, current_x, current_y, word, color_tint_idx);
+                    current_x += self.measure_text_width(word) as i16;
+                }
+            }
+            current_y += self.line_height as i16;
+        }
+    }
+}
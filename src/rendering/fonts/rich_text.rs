@@ -0,0 +1,434 @@
+use std::ops::Range;
+use crate::rendering::blittable::{BlitBuilder, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+use crate::rendering::fonts::font_align::HorizontalAlignment;
+
+/// Glyph metrics and drawing needed to lay out rich text.
+/// Pen y is always the top of a line
+pub trait RichTextFont {
+    fn line_height(&self) -> usize;
+    fn space_width(&self) -> usize;
+    fn char_advance(&self, chr: char) -> i16;
+    fn kerning(&self, _first: char, _second: char) -> i16 { 0 }
+    fn draw_char(
+        &self,
+        buffer: &mut [u8], buffer_width: usize,
+        x: i16, y: i16,
+        chr: char,
+        color_tint_idx: Option<u8>
+    );
+}
+
+/// Icons laid out in a grid of equally sized cells, referred in markup by their index
+/// counting from the top left cell row by row
+pub struct IconSheet<'a> {
+    surface: &'a BlittableSurface,
+    icon_width: usize,
+    icon_height: usize,
+    color_key: u8
+}
+
+impl<'a> IconSheet<'a> {
+    pub fn new(surface: &'a BlittableSurface, icon_width: usize, icon_height: usize) -> Self {
+        Self {
+            surface,
+            icon_width,
+            icon_height,
+            color_key: 0
+        }
+    }
+
+    pub fn with_color_key(self, color_key: u8) -> Self {
+        Self { color_key, ..self }
+    }
+
+    fn draw_icon(&self, buffer: &mut [u8], buffer_width: usize, x: i16, y: i16, icon_idx: usize) {
+        let columns = (self.surface.get_width() / self.icon_width.max(1)).max(1);
+        BlitBuilder::create_ext(buffer, buffer_width, &self.surface.with_color_key(self.color_key))
+            .with_dest_pos(x, y)
+            .with_source_subrect(
+                (icon_idx % columns) * self.icon_width,
+                (icon_idx / columns) * self.icon_height,
+                self.icon_width,
+                self.icon_height
+            )
+            .blit();
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LaidOutGlyphKind {
+    Char(char),
+    Icon(usize)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct LaidOutGlyph {
+    /// position relative to the layout origin
+    pub x: i16,
+    pub y: i16,
+    pub width: usize,
+    pub kind: LaidOutGlyphKind,
+    pub color: Option<u8>
+}
+
+#[derive(Clone, Debug)]
+pub struct LaidOutLine {
+    pub y: i16,
+    pub width: usize,
+    pub glyph_range: Range<usize>
+}
+
+/// A result of RichText::layout. Glyph positions are relative to the layout origin,
+/// so a layout may be computed once and drawn many times, e.g. with a growing
+/// visible glyph count to get a typewriter effect
+#[derive(Clone, Debug, Default)]
+pub struct RichTextLayout {
+    pub glyphs: Vec<LaidOutGlyph>,
+    pub lines: Vec<LaidOutLine>,
+    pub width: usize,
+    pub height: usize,
+    /// true if some text didn't fit into max_lines
+    pub truncated: bool
+}
+
+impl RichTextLayout {
+    pub fn glyph_count(&self) -> usize {
+        self.glyphs.len()
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Token {
+    Glyph(LaidOutGlyphKind, Option<u8>),
+    Space,
+    NewLine
+}
+
+/// Text drawing with word wrap, ellipsis truncation and a small inline markup:
+///
+/// * `{c:12}` switches color to palette index 12, `{c}` switches back to the default color
+/// * `{i:3}` inserts icon 3 from an icon sheet
+/// * `{{` is a literal `{`
+///
+/// ```ignore
+/// let rich_text = RichText::new(&font)
+///     .with_max_width(120)
+///     .with_max_lines(3)
+///     .with_ellipsis();
+/// let layout = rich_text.layout("Press {c:12}fire{c} to continue");
+/// rich_text.draw_partial(ctx, 10, 10, &layout, visible_glyphs);
+/// ```
+pub struct RichText<'a, TFont: RichTextFont> {
+    font: &'a TFont,
+    icons: Option<IconSheet<'a>>,
+    max_width: Option<usize>,
+    max_lines: Option<usize>,
+    ellipsis: bool,
+    alignment: HorizontalAlignment,
+    default_color: Option<u8>
+}
+
+impl<'a, TFont: RichTextFont> RichText<'a, TFont> {
+    pub fn new(font: &'a TFont) -> Self {
+        Self {
+            font,
+            icons: None,
+            max_width: None,
+            max_lines: None,
+            ellipsis: false,
+            alignment: HorizontalAlignment::Left,
+            default_color: None
+        }
+    }
+
+    pub fn with_icons(self, icons: IconSheet<'a>) -> Self {
+        Self { icons: Some(icons), ..self }
+    }
+
+    /// words which don't fit into the width are wrapped to the next line.
+    /// A word longer than the width is broken by characters
+    pub fn with_max_width(self, max_width: usize) -> Self {
+        Self { max_width: Some(max_width), ..self }
+    }
+
+    pub fn with_max_lines(self, max_lines: usize) -> Self {
+        Self { max_lines: Some(max_lines), ..self }
+    }
+
+    /// ends the last line with "..." when the text is truncated by max_lines
+    pub fn with_ellipsis(self) -> Self {
+        Self { ellipsis: true, ..self }
+    }
+
+    /// lines are aligned within max_width, or within the widest line if there is no max_width
+    pub fn with_alignment(self, alignment: HorizontalAlignment) -> Self {
+        Self { alignment, ..self }
+    }
+
+    /// color tint used outside of {c:N} spans. None draws glyphs with their own colors
+    pub fn with_default_color(self, default_color: Option<u8>) -> Self {
+        Self { default_color, ..self }
+    }
+
+    pub fn layout(&self, text: &str) -> RichTextLayout {
+        let tokens = self.parse(text);
+        let line_height = self.font.line_height() as i16;
+        let space_width = self.font.space_width();
+        let max_width = self.max_width.unwrap_or(usize::MAX);
+
+        let mut layout = RichTextLayout::default();
+        let mut line_start = 0;
+        let mut pen_x = 0usize;
+        let mut pending_space = false;
+
+        let mut idx = 0;
+        while idx < tokens.len() {
+            match tokens[idx] {
+                Token::NewLine => {
+                    self.finish_line(&mut layout, &mut line_start, &mut pen_x);
+                    pending_space = false;
+                    idx += 1;
+                },
+                Token::Space => {
+                    pending_space = pen_x > 0;
+                    idx += 1;
+                },
+                Token::Glyph(..) => {
+                    let word_end = tokens[idx..]
+                        .iter()
+                        .position(|it| !matches!(it, Token::Glyph(..)))
+                        .map(|it| idx + it)
+                        .unwrap_or(tokens.len());
+                    let word = &tokens[idx..word_end];
+                    let word_width = self.measure_word(word);
+
+                    let space = if pending_space { space_width } else { 0 };
+                    if pen_x > 0 && pen_x + space + word_width > max_width {
+                        self.finish_line(&mut layout, &mut line_start, &mut pen_x);
+                    } else {
+                        pen_x += space;
+                    }
+                    pending_space = false;
+
+                    let mut previous = None;
+                    for token in word {
+                        if let Token::Glyph(kind, color) = *token {
+                            let advance = self.glyph_advance(previous, kind);
+                            if pen_x > 0 && pen_x + advance.width > max_width {
+                                self.finish_line(&mut layout, &mut line_start, &mut pen_x);
+                                previous = None;
+                            }
+                            let advance = self.glyph_advance(previous, kind);
+                            pen_x = (pen_x as i32 + advance.kerning as i32).max(0) as usize;
+                            layout.glyphs.push(LaidOutGlyph {
+                                x: pen_x as i16,
+                                y: layout.lines.len() as i16 * line_height,
+                                width: advance.width,
+                                kind,
+                                color
+                            });
+                            pen_x += advance.width;
+                            previous = Some(kind);
+                        }
+                    }
+                    idx = word_end;
+                }
+            }
+        }
+        self.finish_line(&mut layout, &mut line_start, &mut pen_x);
+
+        if let Some(max_lines) = self.max_lines {
+            if layout.lines.len() > max_lines {
+                layout.truncated = true;
+                let glyphs_left = match max_lines {
+                    0 => 0,
+                    _ => layout.lines[max_lines - 1].glyph_range.end
+                };
+                layout.lines.truncate(max_lines);
+                layout.glyphs.truncate(glyphs_left);
+                if self.ellipsis && max_lines > 0 {
+                    self.append_ellipsis(&mut layout, max_width);
+                }
+            }
+        }
+
+        layout.width = layout.lines.iter().map(|it| it.width).max().unwrap_or(0);
+        layout.height = layout.lines.len() * line_height as usize;
+        self.align_lines(&mut layout);
+        layout
+    }
+
+    pub fn draw(&self, destination: &mut (impl BufferProviderMut<u8> + SizedSurface), x: i16, y: i16, layout: &RichTextLayout) {
+        self.draw_partial(destination, x, y, layout, layout.glyphs.len());
+    }
+
+    /// draws only the first visible_glyph_count glyphs of the layout
+    pub fn draw_partial(
+        &self,
+        destination: &mut (impl BufferProviderMut<u8> + SizedSurface),
+        x: i16, y: i16,
+        layout: &RichTextLayout,
+        visible_glyph_count: usize
+    ) {
+        let buffer_width = destination.get_width();
+        let buffer = destination.get_buffer_mut();
+        for glyph in layout.glyphs.iter().take(visible_glyph_count) {
+            match glyph.kind {
+                LaidOutGlyphKind::Char(chr) => self.font.draw_char(
+                    buffer, buffer_width,
+                    x + glyph.x, y + glyph.y,
+                    chr,
+                    glyph.color
+                ),
+                LaidOutGlyphKind::Icon(icon_idx) => if let Some(icons) = &self.icons {
+                    let icon_y = y + glyph.y + (self.font.line_height() as i16 - icons.icon_height as i16) / 2;
+                    icons.draw_icon(buffer, buffer_width, x + glyph.x, icon_y, icon_idx);
+                }
+            }
+        }
+    }
+
+    /// lays out and draws the text in one go
+    pub fn draw_text(&self, destination: &mut (impl BufferProviderMut<u8> + SizedSurface), x: i16, y: i16, text: &str) -> RichTextLayout {
+        let layout = self.layout(text);
+        self.draw(destination, x, y, &layout);
+        layout
+    }
+
+    fn parse(&self, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::with_capacity(text.len());
+        let mut color = self.default_color;
+        let mut chars = text.chars().peekable();
+        while let Some(chr) = chars.next() {
+            match chr {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    tokens.push(Token::Glyph(LaidOutGlyphKind::Char('{'), color));
+                },
+                '{' => {
+                    let mut tag = String::new();
+                    for next in chars.by_ref() {
+                        if next == '}' {
+                            break;
+                        }
+                        tag.push(next);
+                    }
+                    match tag.split_once(':') {
+                        None if tag == "c" => color = self.default_color,
+                        Some(("c", value)) => if let Ok(value) = value.trim().parse() {
+                            color = Some(value);
+                        },
+                        Some(("i", value)) => if let Ok(value) = value.trim().parse() {
+                            tokens.push(Token::Glyph(LaidOutGlyphKind::Icon(value), color));
+                        },
+                        _ => {}
+                    }
+                },
+                '\n' => tokens.push(Token::NewLine),
+                c if c.is_whitespace() => tokens.push(Token::Space),
+                c => tokens.push(Token::Glyph(LaidOutGlyphKind::Char(c), color))
+            }
+        }
+        tokens
+    }
+
+    fn glyph_advance(&self, previous: Option<LaidOutGlyphKind>, kind: LaidOutGlyphKind) -> GlyphAdvance {
+        match kind {
+            LaidOutGlyphKind::Char(chr) => GlyphAdvance {
+                width: self.font.char_advance(chr).max(0) as usize,
+                kerning: match previous {
+                    Some(LaidOutGlyphKind::Char(prev)) => self.font.kerning(prev, chr),
+                    _ => 0
+                }
+            },
+            LaidOutGlyphKind::Icon(_) => GlyphAdvance {
+                width: self.icons.as_ref().map(|it| it.icon_width).unwrap_or(0),
+                kerning: 0
+            }
+        }
+    }
+
+    fn measure_word(&self, word: &[Token]) -> usize {
+        let mut width = 0i32;
+        let mut previous = None;
+        for token in word {
+            if let Token::Glyph(kind, _) = *token {
+                let advance = self.glyph_advance(previous, kind);
+                width += advance.width as i32 + advance.kerning as i32;
+                previous = Some(kind);
+            }
+        }
+        width.max(0) as usize
+    }
+
+    fn finish_line(&self, layout: &mut RichTextLayout, line_start: &mut usize, pen_x: &mut usize) {
+        let line_height = self.font.line_height() as i16;
+        layout.lines.push(LaidOutLine {
+            y: layout.lines.len() as i16 * line_height,
+            width: *pen_x,
+            glyph_range: *line_start..layout.glyphs.len()
+        });
+        *line_start = layout.glyphs.len();
+        *pen_x = 0;
+    }
+
+    fn append_ellipsis(&self, layout: &mut RichTextLayout, max_width: usize) {
+        let dot_width = self.font.char_advance('.').max(0) as usize;
+        let ellipsis_width = dot_width * 3;
+        let last_line = match layout.lines.last_mut() {
+            None => return,
+            Some(line) => line
+        };
+        let color = layout.glyphs
+            .get(last_line.glyph_range.end.max(1) - 1)
+            .map(|it| it.color)
+            .unwrap_or(self.default_color);
+
+        while last_line.glyph_range.end > last_line.glyph_range.start {
+            let last = layout.glyphs[last_line.glyph_range.end - 1];
+            if (last.x as usize + last.width).saturating_add(ellipsis_width) <= max_width {
+                break;
+            }
+            layout.glyphs.pop();
+            last_line.glyph_range.end -= 1;
+        }
+
+        let mut pen_x = layout.glyphs[last_line.glyph_range.clone()]
+            .last()
+            .map(|it| it.x as usize + it.width)
+            .unwrap_or(0);
+        for _ in 0..3 {
+            layout.glyphs.push(LaidOutGlyph {
+                x: pen_x as i16,
+                y: last_line.y,
+                width: dot_width,
+                kind: LaidOutGlyphKind::Char('.'),
+                color
+            });
+            pen_x += dot_width;
+        }
+        last_line.glyph_range.end = layout.glyphs.len();
+        last_line.width = pen_x;
+    }
+
+    fn align_lines(&self, layout: &mut RichTextLayout) {
+        let box_width = self.max_width.unwrap_or(layout.width);
+        for line in layout.lines.iter() {
+            let empty_space = box_width as i16 - line.width as i16;
+            let offset = match self.alignment {
+                HorizontalAlignment::Left => 0,
+                HorizontalAlignment::Center => empty_space / 2,
+                HorizontalAlignment::Right => empty_space
+            };
+            for glyph in layout.glyphs[line.glyph_range.clone()].iter_mut() {
+                glyph.x += offset;
+            }
+        }
+    }
+}
+
+struct GlyphAdvance {
+    width: usize,
+    kerning: i16
+}
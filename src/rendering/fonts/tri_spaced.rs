@@ -4,6 +4,7 @@ use crate::format_loaders::im_256::Im256LoadingError;
 use crate::rendering::blittable::{BlitBuilder};
 use crate::rendering::{BlittableSurface};
 use crate::rendering::fonts::font_align::{HorizontalAlignment, VerticalAlignment};
+use crate::rendering::fonts::rich_text::RichTextFont;
 use crate::window::RetroBlitContext;
 
 const DEFAULT_TRISPACED_FONT_BYTES: &[u8] = include_bytes!("default_trispaced_font.im256");
@@ -241,6 +242,41 @@ macro_rules! impl_text_drawer {
 impl_text_drawer!(RetroBlitContext);
 impl_text_drawer!(BlittableSurface);
 
+impl RichTextFont for Font {
+    fn line_height(&self) -> usize {
+        self.font_info.glyph_grid_step_y
+    }
+
+    fn space_width(&self) -> usize {
+        self.font_info.glyph_grid_step_x
+    }
+
+    fn char_advance(&self, chr: char) -> i16 {
+        self.font_info.get_glyph_metrics(chr).width as i16
+    }
+
+    fn draw_char(&self, buffer: &mut [u8], buffer_width: usize, x: i16, y: i16, chr: char, color_tint_idx: Option<u8>) {
+        let height = self.font_info.glyph_grid_step_y;
+        let GlyphMetrics { x_pos, y_pos, width } = self.font_info.get_glyph_metrics(chr);
+        // the same as draw_text does
+        let y = y - self.font_info.upper_cap_offset as i16;
+        match color_tint_idx {
+            None => {
+                BlitBuilder::create_ext(buffer, buffer_width, &self.surface.with_color_key(0))
+                    .with_dest_pos(x, y)
+                    .with_source_subrect(x_pos, y_pos, width, height)
+                    .blit();
+            },
+            Some(idx) => {
+                BlitBuilder::create_ext(buffer, buffer_width, &self.surface.with_color_key_blink(0, idx))
+                    .with_dest_pos(x, y)
+                    .with_source_subrect(x_pos, y_pos, width, height)
+                    .blit();
+            }
+        }
+    }
+}
+
 impl Font {
     pub fn new(font_info: FontInfo, surface: BlittableSurface) -> Self {
         Self {
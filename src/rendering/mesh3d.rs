@@ -0,0 +1,274 @@
+use glam::{Mat3, Mat4, Vec3, vec3};
+use crate::format_loaders::obj::ObjModel;
+use crate::math_utils::bsp_3d::CSG;
+use crate::rendering::blittable::{BufferProviderMut, SizedSurface};
+use crate::rendering::perspective_rendering::{DepthBuffer, PerspectiveTriangleRasterizer, PerspectiveVertex};
+
+/// A range of palette colors going from the darkest to the brightest shade
+#[derive(Copy, Clone, Debug)]
+pub struct ShadeRamp {
+    pub first_color: u8,
+    pub length: u8
+}
+
+impl ShadeRamp {
+    pub fn new(first_color: u8, length: u8) -> Self {
+        Self { first_color, length }
+    }
+
+    /// intensity in 0..=1 range
+    pub fn get_color(&self, intensity: f32) -> u8 {
+        let max_shade = self.length.max(1) - 1;
+        let shade = (intensity.clamp(0.0, 1.0) * max_shade as f32).round() as u8;
+        self.first_color.saturating_add(shade)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ShadingMode {
+    /// one color per triangle computed from its face normal
+    Flat,
+    /// intensity is computed per vertex and interpolated over the triangle
+    Gouraud
+}
+
+pub struct Camera {
+    position: Vec3,
+    target: Vec3,
+    up: Vec3,
+    fov_y: f32,
+    aspect_ratio: f32,
+    near: f32,
+    far: f32
+}
+
+impl Camera {
+    pub fn new(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        Self {
+            position: vec3(0.0, 0.0, 1.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            fov_y,
+            aspect_ratio,
+            near,
+            far
+        }
+    }
+
+    pub fn with_position(self, position: Vec3) -> Self {
+        Self { position, ..self }
+    }
+
+    pub fn with_target(self, target: Vec3) -> Self {
+        Self { target, ..self }
+    }
+
+    pub fn with_up(self, up: Vec3) -> Self {
+        Self { up, ..self }
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    pub fn set_target(&mut self, target: Vec3) {
+        self.target = target;
+    }
+
+    pub fn get_position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn get_target(&self) -> Vec3 {
+        self.target
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position, self.target, self.up)
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        Mat4::perspective_rh(self.fov_y, self.aspect_ratio, self.near, self.far)
+    }
+
+    pub fn view_projection_matrix(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MeshVertex {
+    pub position: Vec3,
+    pub normal: Vec3
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MeshTriangle {
+    pub indices: [usize; 3],
+    pub ramp: ShadeRamp
+}
+
+/// Triangle mesh with counter-clockwise front faces
+#[derive(Clone, Debug, Default)]
+pub struct Mesh3d {
+    pub vertices: Vec<MeshVertex>,
+    pub triangles: Vec<MeshTriangle>
+}
+
+impl Mesh3d {
+    /// polygons are triangulated as fans, ramp_selector maps polygon shared data to a palette ramp
+    pub fn from_csg<TShared: Copy>(csg: &CSG<TShared>, ramp_selector: impl Fn(TShared) -> ShadeRamp) -> Self {
+        let mut mesh = Mesh3d::default();
+        for polygon in csg.polygons.iter() {
+            if polygon.vertices.len() < 3 {
+                continue;
+            }
+            let first = mesh.vertices.len();
+            mesh.vertices.extend(polygon.vertices.iter().map(|it| MeshVertex {
+                position: it.pos,
+                normal: it.normal
+            }));
+            let ramp = ramp_selector(polygon.shared);
+            for i in 2..polygon.vertices.len() {
+                mesh.triangles.push(MeshTriangle {
+                    indices: [first, first + i - 1, first + i],
+                    ramp
+                });
+            }
+        }
+        mesh
+    }
+
+    /// all objects of a model are merged into one mesh. Missing normals are computed
+    /// by averaging normals of faces sharing a position
+    pub fn from_obj(model: &ObjModel, ramp: ShadeRamp) -> Self {
+        let mut smooth_normals = vec![Vec3::ZERO; model.positions.len()];
+        for face in model.objects.iter().flat_map(|it| it.faces.iter()) {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|it| Vec3::from(model.positions[it.position]));
+            let normal = (b - a).cross(c - a);
+            for idx in face.iter() {
+                smooth_normals[idx.position] += normal;
+            }
+        }
+
+        let mut mesh = Mesh3d::default();
+        for face in model.objects.iter().flat_map(|it| it.faces.iter()) {
+            let first = mesh.vertices.len();
+            mesh.vertices.extend(face.iter().map(|idx| MeshVertex {
+                position: Vec3::from(model.positions[idx.position]),
+                normal: idx.normal
+                    .map(|it| Vec3::from(model.normals[it]))
+                    .unwrap_or(smooth_normals[idx.position])
+                    .normalize_or_zero()
+            }));
+            for i in 2..face.len() {
+                mesh.triangles.push(MeshTriangle {
+                    indices: [first, first + i - 1, first + i],
+                    ramp
+                });
+            }
+        }
+        mesh
+    }
+}
+
+/// Draws meshes into an indexed buffer, mapping light intensity onto palette shade ramps.
+/// Without a depth buffer triangles are sorted back to front
+pub struct MeshRenderer<'a> {
+    camera: &'a Camera,
+    light_direction: Vec3,
+    ambient: f32,
+    shading: ShadingMode,
+    backface_culling: bool,
+    depth_buffer: Option<&'a mut DepthBuffer>
+}
+
+impl<'a> MeshRenderer<'a> {
+    pub fn create(camera: &'a Camera) -> Self {
+        Self {
+            camera,
+            light_direction: vec3(0.0, 0.0, -1.0),
+            ambient: 0.2,
+            shading: ShadingMode::Flat,
+            backface_culling: true,
+            depth_buffer: None
+        }
+    }
+
+    /// direction the light travels in, in world space
+    pub fn with_light_direction(self, light_direction: Vec3) -> Self {
+        Self { light_direction: light_direction.normalize_or_zero(), ..self }
+    }
+
+    /// lowest intensity of unlit faces, 0..=1
+    pub fn with_ambient(self, ambient: f32) -> Self {
+        Self { ambient: ambient.clamp(0.0, 1.0), ..self }
+    }
+
+    pub fn with_shading(self, shading: ShadingMode) -> Self {
+        Self { shading, ..self }
+    }
+
+    pub fn with_backface_culling(self, backface_culling: bool) -> Self {
+        Self { backface_culling, ..self }
+    }
+
+    /// should be of the destination size, drawing panics otherwise
+    pub fn with_depth_buffer(self, depth_buffer: &'a mut DepthBuffer) -> Self {
+        Self { depth_buffer: Some(depth_buffer), ..self }
+    }
+
+    pub fn draw(&mut self, destination: &mut (impl BufferProviderMut<u8> + SizedSurface), mesh: &Mesh3d, model: Mat4) {
+        let view_projection = self.camera.view_projection_matrix();
+        let normal_matrix = Mat3::from_mat4(model).inverse().transpose();
+        let camera_position = self.camera.get_position();
+
+        let world_positions = mesh.vertices
+            .iter()
+            .map(|it| model.transform_point3(it.position))
+            .collect::<Vec<_>>();
+        let intensities = mesh.vertices
+            .iter()
+            .map(|it| self.intensity((normal_matrix * it.normal).normalize_or_zero()))
+            .collect::<Vec<_>>();
+
+        let mut visible = Vec::with_capacity(mesh.triangles.len());
+        for triangle in mesh.triangles.iter() {
+            let [a, b, c] = triangle.indices.map(|it| world_positions[it]);
+            let face_normal = (b - a).cross(c - a).normalize_or_zero();
+            if self.backface_culling && face_normal.dot(a - camera_position) >= 0.0 {
+                continue;
+            }
+            let distance = (a + b + c).distance_squared(camera_position * 3.0);
+            visible.push((triangle, self.intensity(face_normal), distance));
+        }
+
+        if self.depth_buffer.is_none() {
+            visible.sort_by(|l, r| r.2.partial_cmp(&l.2).unwrap_or(std::cmp::Ordering::Equal));
+        }
+
+        let shading = self.shading;
+        let mut rasterizer = PerspectiveTriangleRasterizer::create(destination);
+        if let Some(depth_buffer) = self.depth_buffer.as_deref_mut() {
+            rasterizer = rasterizer.with_depth_buffer(depth_buffer);
+        }
+
+        for (triangle, face_intensity, _) in visible {
+            let shades = match shading {
+                ShadingMode::Flat => [face_intensity; 3],
+                ShadingMode::Gouraud => triangle.indices.map(|it| intensities[it])
+            };
+            // intensity travels through the u coordinate to get it perspective-correct
+            let vertices = [0, 1, 2].map(|i| PerspectiveVertex {
+                position: view_projection * world_positions[triangle.indices[i]].extend(1.0),
+                uv: (shades[i], 0.0)
+            });
+            let ramp = triangle.ramp;
+            rasterizer.clip_and_rasterize(vertices, |pix, intensity, _| *pix = ramp.get_color(intensity));
+        }
+    }
+
+    fn intensity(&self, normal: Vec3) -> f32 {
+        self.ambient + (1.0 - self.ambient) * (-self.light_direction).dot(normal).max(0.0)
+    }
+}
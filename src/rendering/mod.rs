@@ -1,10 +1,23 @@
 pub mod blittable;
+pub mod compiled_sprite;
 pub mod fonts;
 pub mod deformed_rendering;
+pub mod perspective_rendering;
+pub mod mesh3d;
+pub mod portal_rendering;
+pub mod raycaster;
+pub mod shade_table;
+pub mod mode7;
+pub mod voxel_terrain;
 pub mod bresenham;
 pub mod tessellation;
 pub mod transform;
+pub mod raster_batch;
+pub mod draw_list;
+pub mod particles;
 pub mod shapes;
+pub mod regions;
+pub mod nine_slice;
 
 use crate::format_loaders::bmp_256::Bmp;
 use crate::format_loaders::im_256::Image;
@@ -34,6 +47,14 @@ impl BlittableSurface {
         }
     }
 
+    /// every source color is remapped through the lookup table, e.g. a level of a shade table
+    pub fn with_lut<'a>(&'a self, lut: &'a [u8; 256]) -> LutWrapper<'a> {
+        LutWrapper{
+            wrapped: self,
+            lut
+        }
+    }
+
     pub fn with_color_key_blink(&self, color_key: u8, blink_color: u8) -> ColorKeyBlinkWrapper {
         ColorKeyBlinkWrapper{
             wrapped: self,
@@ -79,7 +100,12 @@ impl From<&Bmp> for BlittableSurface {
     }
 }
 
-impl Blittable<u8> for BlittableSurface {}
+impl Blittable<u8> for BlittableSurface {
+    #[inline(always)]
+    fn blend_row(&self, dst: &mut [u8], src: &[u8]) {
+        dst.copy_from_slice(src);
+    }
+}
 
 pub struct ColorKeyWrapper<'a> {
     wrapped: &'a BlittableSurface,
@@ -109,6 +135,50 @@ impl Blittable<u8> for ColorKeyWrapper<'_> {
     }
 }
 
+pub struct LutWrapper<'a> {
+    wrapped: &'a BlittableSurface,
+    lut: &'a [u8; 256]
+}
+
+impl SizedSurface for LutWrapper<'_> {
+    fn get_width(&self) -> usize {
+        self.wrapped.get_width()
+    }
+
+    fn get_height(&self) -> usize {
+        self.wrapped.get_height()
+    }
+}
+
+impl BufferProvider<u8> for LutWrapper<'_> {
+    fn get_buffer(&self) -> &[u8] {
+        self.wrapped.get_buffer()
+    }
+}
+
+impl Blittable<u8> for LutWrapper<'_> {
+    #[inline(always)]
+    fn blend_function(&self, dst: &mut u8, src: &u8) {
+        *dst = self.lut[*src as usize];
+    }
+
+    #[inline(always)]
+    fn blend_row(&self, dst: &mut [u8], src: &[u8]) {
+        let lut = self.lut;
+        for (d, s) in dst.iter_mut().zip(src) {
+            *d = lut[*s as usize];
+        }
+    }
+
+    #[inline(always)]
+    fn blend_row_reversed(&self, dst: &mut [u8], src: &[u8]) {
+        let lut = self.lut;
+        for (d, s) in dst.iter_mut().zip(src.iter().rev()) {
+            *d = lut[*s as usize];
+        }
+    }
+}
+
 pub struct ColorKeyBlinkWrapper<'a> {
     wrapped: &'a BlittableSurface,
     blink_color: u8,
@@ -138,8 +208,8 @@ impl Blittable<u8> for ColorKeyBlinkWrapper<'_> {
     }
 }
 
-impl<'a, TBlittable: Blittable<u8>> blittable::BlitDestination<'a, u8, TBlittable> for BlittableSurface {
+impl<'a, TBlittable: Blittable<u8> + ?Sized> blittable::BlitDestination<'a, u8, TBlittable> for BlittableSurface {
 }
 
-impl<'a, TBlittable: Blittable<u8>> blittable::BlitDestination<'a, u8, TBlittable> for crate::window::RetroBlitContext {
+impl<'a, TBlittable: Blittable<u8> + ?Sized> blittable::BlitDestination<'a, u8, TBlittable> for crate::window::RetroBlitContext {
 }
\ No newline at end of file
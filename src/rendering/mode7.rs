@@ -0,0 +1,172 @@
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+
+/// Anything that can be laid on the plane: a surface or a tilemap
+pub trait Mode7Source: SizedSurface {
+    /// x and y are always within get_width() and get_height()
+    fn get_texel(&self, x: usize, y: usize) -> u8;
+}
+
+impl Mode7Source for BlittableSurface {
+    #[inline(always)]
+    fn get_texel(&self, x: usize, y: usize) -> u8 {
+        self.get_buffer()[y * self.get_width() + x]
+    }
+}
+
+/// A tilemap where every tile is a tile_size x tile_size square of a tileset surface.
+/// Tiles are numbered row by row from the top left of the tileset
+pub struct Mode7Tilemap<'a> {
+    tileset: &'a BlittableSurface,
+    tile_size: usize,
+    map_width: usize,
+    map_height: usize,
+    tiles: &'a [u16]
+}
+
+impl<'a> Mode7Tilemap<'a> {
+    pub fn new(tileset: &'a BlittableSurface, tile_size: usize, map_width: usize, map_height: usize, tiles: &'a [u16]) -> Self {
+        Self {
+            tileset,
+            tile_size: tile_size.max(1),
+            map_width,
+            map_height,
+            tiles
+        }
+    }
+}
+
+impl<'a> SizedSurface for Mode7Tilemap<'a> {
+    fn get_width(&self) -> usize { self.map_width * self.tile_size }
+
+    fn get_height(&self) -> usize { self.map_height * self.tile_size }
+}
+
+impl<'a> Mode7Source for Mode7Tilemap<'a> {
+    #[inline(always)]
+    fn get_texel(&self, x: usize, y: usize) -> u8 {
+        let tile = self.tiles
+            .get((y / self.tile_size) * self.map_width + x / self.tile_size)
+            .copied()
+            .unwrap_or(0) as usize;
+        let columns = (self.tileset.get_width() / self.tile_size).max(1);
+        let tx = (tile % columns) * self.tile_size + x % self.tile_size;
+        let ty = (tile / columns) * self.tile_size + y % self.tile_size;
+        if tx >= self.tileset.get_width() || ty >= self.tileset.get_height() {
+            return 0;
+        }
+        self.tileset.get_texel(tx, ty)
+    }
+}
+
+/// What is drawn where the plane goes beyond the source
+#[derive(Copy, Clone)]
+pub enum Mode7Outside {
+    Repeat,
+    Color(u8),
+    /// pixels are left untouched
+    Transparent
+}
+
+/// Camera above the plane. Plane coordinates are source texels, y grows downwards
+#[derive(Copy, Clone, Debug)]
+pub struct Mode7Camera {
+    pub position: (f32, f32),
+    pub height: f32,
+    /// in radians, 0 looks along +x
+    pub yaw: f32,
+    /// screen row of the horizon, only rows below it are drawn
+    pub horizon: f32,
+    /// distance from the eye to the screen in pixels, bigger values zoom in
+    pub focal_length: f32
+}
+
+pub struct Mode7Renderer<'a, TSource: Mode7Source> {
+    source: &'a TSource,
+    outside: Mode7Outside
+}
+
+impl<'a, TSource: Mode7Source> Mode7Renderer<'a, TSource> {
+    pub fn create(source: &'a TSource) -> Self {
+        Self {
+            source,
+            outside: Mode7Outside::Repeat
+        }
+    }
+
+    pub fn with_outside(self, outside: Mode7Outside) -> Self {
+        Self { outside, ..self }
+    }
+
+    pub fn draw(&self, destination: &mut (impl BufferProviderMut<u8> + SizedSurface), camera: &Mode7Camera) {
+        let first_row = ((camera.horizon - 0.5).floor() + 1.0).max(0.0) as usize;
+        self.draw_impl(destination, camera, first_row, |_, _| {});
+    }
+
+    /// callback gets a copy of the camera for every row of the screen and may alter it,
+    /// e.g. to bend the horizon or to make a wavy water effect.
+    /// Rows above the horizon of their camera are left untouched
+    pub fn draw_with_scanline_callback(
+        &self,
+        destination: &mut (impl BufferProviderMut<u8> + SizedSurface),
+        camera: &Mode7Camera,
+        callback: impl FnMut(usize, &mut Mode7Camera)
+    ) {
+        // the callback may raise the horizon, so every row has to be asked
+        self.draw_impl(destination, camera, 0, callback);
+    }
+
+    fn draw_impl(
+        &self,
+        destination: &mut (impl BufferProviderMut<u8> + SizedSurface),
+        camera: &Mode7Camera,
+        first_row: usize,
+        mut callback: impl FnMut(usize, &mut Mode7Camera)
+    ) {
+        let width = destination.get_width();
+        let height = destination.get_height();
+        let (source_width, source_height) = (self.source.get_width() as i32, self.source.get_height() as i32);
+        if source_width == 0 || source_height == 0 {
+            return;
+        }
+        let buffer = destination.get_buffer_mut();
+
+        for y in first_row..height {
+            let mut row_camera = *camera;
+            callback(y, &mut row_camera);
+
+            let rows_below_horizon = y as f32 + 0.5 - row_camera.horizon;
+            if rows_below_horizon <= 0.0 {
+                continue;
+            }
+            let distance = row_camera.height * row_camera.focal_length / rows_below_horizon;
+            let (sin, cos) = row_camera.yaw.sin_cos();
+
+            // every row is an affine span over the plane
+            let step = (-sin * distance / row_camera.focal_length, cos * distance / row_camera.focal_length);
+            let left_offset = 0.5 - width as f32 / 2.0;
+            let mut u = row_camera.position.0 + cos * distance + step.0 * left_offset;
+            let mut v = row_camera.position.1 + sin * distance + step.1 * left_offset;
+
+            for pix in buffer[y * width..(y + 1) * width].iter_mut() {
+                let (tx, ty) = (u.floor() as i32, v.floor() as i32);
+                u += step.0;
+                v += step.1;
+                if (0..source_width).contains(&tx) && (0..source_height).contains(&ty) {
+                    *pix = self.source.get_texel(tx as usize, ty as usize);
+                    continue;
+                }
+                match self.outside {
+                    Mode7Outside::Repeat => {
+                        *pix = self.source.get_texel(
+                            tx.rem_euclid(source_width) as usize,
+                            ty.rem_euclid(source_height) as usize
+                        );
+                    },
+                    Mode7Outside::Color(color) => *pix = color,
+                    Mode7Outside::Transparent => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::rendering::blittable::{BlitBuilder, Blittable, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+
+/// A panel image cut into a 3x3 grid by the border insets. Corners are drawn as is,
+/// edges and the center are tiled to fill any size
+#[derive(Copy, Clone)]
+pub struct NineSlice<'a> {
+    surface: &'a BlittableSurface,
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+    color_key: Option<u8>
+}
+
+impl<'a> NineSlice<'a> {
+    pub fn new(surface: &'a BlittableSurface, left: usize, top: usize, right: usize, bottom: usize) -> Self {
+        let (width, height) = (surface.get_width(), surface.get_height());
+        let left = left.min(width);
+        let top = top.min(height);
+        Self {
+            surface,
+            left,
+            top,
+            right: right.min(width - left),
+            bottom: bottom.min(height - top),
+            color_key: None
+        }
+    }
+
+    pub fn with_color_key(self, color_key: u8) -> Self {
+        Self { color_key: Some(color_key), ..self }
+    }
+
+    /// (left, top, right, bottom)
+    pub fn get_insets(&self) -> (usize, usize, usize, usize) {
+        (self.left, self.top, self.right, self.bottom)
+    }
+
+    /// panels smaller than the borders lose the edges and then cut the corners
+    pub fn draw(&self, dest: &mut (impl BufferProviderMut<u8> + SizedSurface), x: i16, y: i16, width: usize, height: usize) {
+        let buffer_width = dest.get_width();
+        if buffer_width == 0 {
+            return;
+        }
+        let buffer = dest.get_buffer_mut();
+        match self.color_key {
+            Some(color_key) =>
+                self.draw_impl(buffer, buffer_width, &self.surface.with_color_key(color_key), (x, y), (width, height)),
+            None => self.draw_impl(buffer, buffer_width, self.surface, (x, y), (width, height))
+        }
+    }
+
+    fn draw_impl(
+        &self,
+        buffer: &mut [u8],
+        buffer_width: usize,
+        drawable: &dyn Blittable<u8>,
+        (x, y): (i16, i16),
+        (width, height): (usize, usize)
+    ) {
+        let (src_width, src_height) = (self.surface.get_width(), self.surface.get_height());
+        let src_columns = [
+            (0, self.left),
+            (self.left, src_width - self.left - self.right),
+            (src_width - self.right, self.right)
+        ];
+        let src_rows = [
+            (0, self.top),
+            (self.top, src_height - self.top - self.bottom),
+            (src_height - self.bottom, self.bottom)
+        ];
+        let dst_columns = split_span(width, self.left, self.right);
+        let dst_rows = split_span(height, self.top, self.bottom);
+
+        for (&(src_y, src_h), &(dst_y, dst_h)) in src_rows.iter().zip(dst_rows.iter()) {
+            for (&(src_x, src_w), &(dst_x, dst_w)) in src_columns.iter().zip(dst_columns.iter()) {
+                if src_w == 0 || src_h == 0 {
+                    continue;
+                }
+                let mut tile_y = 0;
+                while tile_y < dst_h {
+                    let tile_h = src_h.min(dst_h - tile_y);
+                    let mut tile_x = 0;
+                    while tile_x < dst_w {
+                        let tile_w = src_w.min(dst_w - tile_x);
+                        BlitBuilder::create_ext(&mut *buffer, buffer_width, drawable)
+                            .with_source_subrect(src_x, src_y, tile_w, tile_h)
+                            .with_dest_pos(
+                                (x as isize + (dst_x + tile_x) as isize) as i16,
+                                (y as isize + (dst_y + tile_y) as isize) as i16
+                            )
+                            .blit();
+                        tile_x += tile_w;
+                    }
+                    tile_y += tile_h;
+                }
+            }
+        }
+    }
+}
+
+/// (offset, length) of the leading border, the middle and the trailing border
+fn split_span(length: usize, leading: usize, trailing: usize) -> [(usize, usize); 3] {
+    let leading = leading.min(length);
+    let trailing = trailing.min(length - leading);
+    let middle = length - leading - trailing;
+    [(0, leading), (leading, middle), (leading + middle, trailing)]
+}
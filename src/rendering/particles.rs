@@ -0,0 +1,321 @@
+use crate::rendering::blittable::{BlitBuilder, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+
+/// Palette range walked through over a particle lifetime, may go in both directions
+#[derive(Copy, Clone, Debug)]
+pub struct ColorRamp {
+    pub first_color: u8,
+    pub last_color: u8
+}
+
+impl ColorRamp {
+    pub fn new(first_color: u8, last_color: u8) -> Self {
+        Self { first_color, last_color }
+    }
+
+    pub fn single(color: u8) -> Self {
+        Self::new(color, color)
+    }
+
+    /// t goes from 0 at birth to 1 at death
+    pub fn get_color(&self, t: f32) -> u8 {
+        let (first, last) = (self.first_color as f32, self.last_color as f32);
+        let steps = (last - first).abs() + 1.0;
+        let step = (t.clamp(0.0, 1.0) * steps).min(steps - 1.0).floor();
+        if last >= first {
+            (first + step) as u8
+        } else {
+            (first - step) as u8
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ParticleLook {
+    Pixel,
+    /// a square of the given size centered at the particle
+    Square(u8),
+    /// a sprite from the slice passed to ParticleSystem::draw, centered at the particle.
+    /// Tinted sprites draw their opaque pixels with the ramp color
+    Sprite { sprite_idx: usize, color_key: u8, tinted: bool }
+}
+
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    pub position: (f32, f32),
+    pub active: bool,
+    /// particles per second while active
+    rate: f32,
+    lifetime: (f32, f32),
+    /// in radians, 0 points along +x
+    direction: f32,
+    spread: f32,
+    speed: (f32, f32),
+    gravity: (f32, f32),
+    spawn_radius: f32,
+    color_ramp: ColorRamp,
+    look: ParticleLook,
+    spawn_accumulator: f32
+}
+
+impl ParticleEmitter {
+    pub fn new(position: (f32, f32)) -> Self {
+        Self {
+            position,
+            active: true,
+            rate: 0.0,
+            lifetime: (1.0, 1.0),
+            direction: 0.0,
+            spread: std::f32::consts::TAU,
+            speed: (10.0, 10.0),
+            gravity: (0.0, 0.0),
+            spawn_radius: 0.0,
+            color_ramp: ColorRamp::single(15),
+            look: ParticleLook::Pixel,
+            spawn_accumulator: 0.0
+        }
+    }
+
+    /// continuous emission in particles per second, 0 emits only on bursts
+    pub fn with_rate(self, rate: f32) -> Self {
+        Self { rate: rate.max(0.0), ..self }
+    }
+
+    /// every particle lives a random time between min and max seconds
+    pub fn with_lifetime(self, min: f32, max: f32) -> Self {
+        Self { lifetime: (min.max(0.0), max.max(min).max(0.0)), ..self }
+    }
+
+    /// particles fly in the direction with a random deviation of up to spread / 2 to both sides
+    pub fn with_direction(self, direction: f32, spread: f32) -> Self {
+        Self { direction, spread, ..self }
+    }
+
+    /// pixels per second
+    pub fn with_speed(self, min: f32, max: f32) -> Self {
+        Self { speed: (min, max.max(min)), ..self }
+    }
+
+    /// pixels per second squared
+    pub fn with_gravity(self, gravity: (f32, f32)) -> Self {
+        Self { gravity, ..self }
+    }
+
+    /// particles are spawned within a circle of the radius around the emitter position
+    pub fn with_spawn_radius(self, spawn_radius: f32) -> Self {
+        Self { spawn_radius: spawn_radius.max(0.0), ..self }
+    }
+
+    pub fn with_color_ramp(self, color_ramp: ColorRamp) -> Self {
+        Self { color_ramp, ..self }
+    }
+
+    pub fn with_look(self, look: ParticleLook) -> Self {
+        Self { look, ..self }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EmitterHandle(usize);
+
+#[derive(Copy, Clone)]
+struct Particle {
+    position: (f32, f32),
+    velocity: (f32, f32),
+    age: f32,
+    lifetime: f32,
+    emitter: usize
+}
+
+/// Particles live in a pool of fixed capacity, new particles are dropped when it is full
+pub struct ParticleSystem {
+    emitters: Vec<ParticleEmitter>,
+    particles: Vec<Particle>,
+    capacity: usize,
+    rng_state: u32
+}
+
+impl ParticleSystem {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            emitters: Vec::new(),
+            particles: Vec::with_capacity(capacity),
+            capacity,
+            rng_state: 0x2545_f491
+        }
+    }
+
+    pub fn with_seed(self, seed: u32) -> Self {
+        // xorshift gets stuck at zero
+        Self { rng_state: seed.max(1), ..self }
+    }
+
+    pub fn add_emitter(&mut self, emitter: ParticleEmitter) -> EmitterHandle {
+        self.emitters.push(emitter);
+        EmitterHandle(self.emitters.len() - 1)
+    }
+
+    pub fn get_emitter(&self, handle: EmitterHandle) -> Option<&ParticleEmitter> {
+        self.emitters.get(handle.0)
+    }
+
+    pub fn get_emitter_mut(&mut self, handle: EmitterHandle) -> Option<&mut ParticleEmitter> {
+        self.emitters.get_mut(handle.0)
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// removes all particles, emitters stay
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// spawns count particles at once, even if the emitter is not active
+    pub fn burst(&mut self, handle: EmitterHandle, count: usize) {
+        if handle.0 >= self.emitters.len() {
+            return;
+        }
+        for _ in 0..count {
+            if !self.spawn(handle.0) {
+                break;
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let mut idx = 0;
+        while idx < self.particles.len() {
+            let particle = &mut self.particles[idx];
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                self.particles.swap_remove(idx);
+                continue;
+            }
+            let gravity = self.emitters[particle.emitter].gravity;
+            particle.velocity.0 += gravity.0 * dt;
+            particle.velocity.1 += gravity.1 * dt;
+            particle.position.0 += particle.velocity.0 * dt;
+            particle.position.1 += particle.velocity.1 * dt;
+            idx += 1;
+        }
+
+        for emitter_idx in 0..self.emitters.len() {
+            let emitter = &mut self.emitters[emitter_idx];
+            if !emitter.active || emitter.rate <= 0.0 {
+                emitter.spawn_accumulator = 0.0;
+                continue;
+            }
+            emitter.spawn_accumulator += emitter.rate * dt;
+            let count = emitter.spawn_accumulator.floor();
+            emitter.spawn_accumulator -= count;
+            for _ in 0..count as usize {
+                if !self.spawn(emitter_idx) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// sprites are referred by sprite particles by their index
+    pub fn draw(&self, dest: &mut (impl BufferProviderMut<u8> + SizedSurface), sprites: &[BlittableSurface]) {
+        let (width, height) = (dest.get_width() as i32, dest.get_height() as i32);
+        if width == 0 {
+            return;
+        }
+        let buffer = dest.get_buffer_mut();
+        for particle in self.particles.iter() {
+            let emitter = &self.emitters[particle.emitter];
+            let color = emitter.color_ramp.get_color(particle.age / particle.lifetime);
+            let (x, y) = (particle.position.0.floor() as i32, particle.position.1.floor() as i32);
+            match emitter.look {
+                ParticleLook::Pixel => {
+                    if (0..width).contains(&x) && (0..height).contains(&y) {
+                        buffer[(y * width + x) as usize] = color;
+                    }
+                },
+                ParticleLook::Square(size) => {
+                    let (start_x, start_y) = (x.saturating_sub(size as i32 / 2), y.saturating_sub(size as i32 / 2));
+                    let (left, top) = (start_x.clamp(0, width), start_y.clamp(0, height));
+                    let (right, bottom) = (
+                        start_x.saturating_add(size as i32).clamp(0, width),
+                        start_y.saturating_add(size as i32).clamp(0, height)
+                    );
+                    if left >= right || top >= bottom {
+                        continue;
+                    }
+                    for row in top..bottom {
+                        for px in &mut buffer[(row * width + left) as usize..(row * width + right) as usize] {
+                            *px = color;
+                        }
+                    }
+                },
+                ParticleLook::Sprite { sprite_idx, color_key, tinted } => {
+                    let sprite = match sprites.get(sprite_idx) {
+                        Some(sprite) => sprite,
+                        None => continue
+                    };
+                    let (sx, sy) = (
+                        x.saturating_sub(sprite.get_width() as i32 / 2).clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+                        y.saturating_sub(sprite.get_height() as i32 / 2).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+                    );
+                    if tinted {
+                        BlitBuilder::create_ext(&mut *buffer, width as usize, &sprite.with_color_key_blink(color_key, color))
+                            .with_dest_pos(sx, sy)
+                            .blit();
+                    } else {
+                        BlitBuilder::create_ext(&mut *buffer, width as usize, &sprite.with_color_key(color_key))
+                            .with_dest_pos(sx, sy)
+                            .blit();
+                    }
+                }
+            }
+        }
+    }
+
+    fn spawn(&mut self, emitter_idx: usize) -> bool {
+        if self.particles.len() >= self.capacity {
+            return false;
+        }
+        let emitter = self.emitters[emitter_idx].clone();
+        let lifetime = self.random_range(emitter.lifetime.0, emitter.lifetime.1);
+        if lifetime <= 0.0 {
+            return true;
+        }
+        let angle = emitter.direction + (self.random() - 0.5) * emitter.spread;
+        let speed = self.random_range(emitter.speed.0, emitter.speed.1);
+        let spawn_angle = self.random() * std::f32::consts::TAU;
+        // square root keeps the spawn area evenly filled
+        let spawn_distance = self.random().sqrt() * emitter.spawn_radius;
+        self.particles.push(Particle {
+            position: (
+                emitter.position.0 + spawn_angle.cos() * spawn_distance,
+                emitter.position.1 + spawn_angle.sin() * spawn_distance
+            ),
+            velocity: (angle.cos() * speed, angle.sin() * speed),
+            age: 0.0,
+            lifetime,
+            emitter: emitter_idx
+        });
+        true
+    }
+
+    /// 0..1
+    fn random(&mut self) -> f32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    fn random_range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.random()
+    }
+}
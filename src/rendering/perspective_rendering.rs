@@ -0,0 +1,270 @@
+use glam::{Vec4, vec4};
+use crate::rendering::blittable::{Blittable, BufferProvider, BufferProviderMut, SizedSurface};
+
+/// A vertex in clip space, e.g. a model position multiplied by
+/// `Mat4::perspective_rh(..) * view * model`, so depth is expected in 0..w range
+#[derive(Copy, Clone)]
+pub struct PerspectiveVertex {
+    pub position: Vec4,
+    /// texture coordinates in texels. They wrap around the texture size
+    pub uv: (f32, f32)
+}
+
+/// 16-bit depth surface. Smaller values are closer to the camera
+pub struct DepthBuffer {
+    width: usize,
+    height: usize,
+    buffer: Vec<u16>
+}
+
+impl DepthBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            buffer: vec![u16::MAX; width * height]
+        }
+    }
+
+    /// should be called before drawing every frame
+    pub fn clear(&mut self) {
+        self.buffer.fill(u16::MAX);
+    }
+}
+
+impl SizedSurface for DepthBuffer {
+    fn get_width(&self) -> usize { self.width }
+
+    fn get_height(&self) -> usize { self.height }
+}
+
+impl BufferProvider<u16> for DepthBuffer {
+    fn get_buffer(&self) -> &[u16] {
+        &self.buffer
+    }
+}
+
+impl BufferProviderMut<u16> for DepthBuffer {
+    fn get_buffer_mut(&mut self) -> &mut [u16] {
+        &mut self.buffer
+    }
+}
+
+#[derive(Copy, Clone)]
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    // (depth, 1/w, u/w, v/w) - all of them are affine in screen space
+    attributes: Vec4
+}
+
+pub struct PerspectiveTriangleRasterizer<'a, T: Copy> {
+    buffer: &'a mut [T],
+    buffer_width: usize,
+    buffer_height: usize,
+    depth_buffer: Option<&'a mut [u16]>
+}
+
+impl<'a, T: Copy> PerspectiveTriangleRasterizer<'a, T> {
+    pub fn create(buffer_provider: &'a mut (impl BufferProviderMut<T>+SizedSurface)) -> Self {
+        let buffer_width = buffer_provider.get_width();
+        let buffer = buffer_provider.get_buffer_mut();
+        let buffer_height = buffer.len() / buffer_width;
+        Self {
+            buffer,
+            buffer_width,
+            buffer_height,
+            depth_buffer: None
+        }
+    }
+
+    /// enables depth test and depth writes. Panics if the depth buffer is not of the destination size
+    pub fn with_depth_buffer(self, depth_buffer: &'a mut DepthBuffer) -> Self {
+        assert!(
+            depth_buffer.get_width() == self.buffer_width && depth_buffer.get_height() == self.buffer_height,
+            "depth buffer is {}x{}, but the destination is {}x{}",
+            depth_buffer.get_width(), depth_buffer.get_height(), self.buffer_width, self.buffer_height
+        );
+        Self {
+            depth_buffer: Some(depth_buffer.get_buffer_mut()),
+            ..self
+        }
+    }
+
+    pub fn rasterize_with_color(
+        self,
+        color: T,
+        vertices: &[PerspectiveVertex],
+        indices: &[u16]
+    ) {
+        self.rasterize_with_color_iter(
+            indices
+                .chunks_exact(3)
+                .map(|it| ([
+                    vertices[it[0] as usize],
+                    vertices[it[1] as usize],
+                    vertices[it[2] as usize]
+                ], color))
+        );
+    }
+
+    pub fn rasterize_with_color_iter(mut self, triangles: impl IntoIterator<Item=([PerspectiveVertex; 3], T)>) {
+        for (triangle, color) in triangles.into_iter() {
+            self.clip_and_rasterize(triangle, |pix, _, _| *pix = color);
+        }
+    }
+
+    pub fn rasterize_with_surface(
+        self,
+        drawable: &'a impl Blittable<T>,
+        vertices: &[PerspectiveVertex],
+        indices: &[u16]
+    ) {
+        self.rasterize_with_surface_iter(
+            indices
+                .chunks_exact(3)
+                .map(|it| ([
+                    vertices[it[0] as usize],
+                    vertices[it[1] as usize],
+                    vertices[it[2] as usize]
+                ], drawable))
+        );
+    }
+
+    pub fn rasterize_with_surface_iter(
+        mut self,
+        triangles: impl IntoIterator<Item=([PerspectiveVertex; 3], &'a (impl Blittable<T> + 'a))>
+    ) {
+        for (triangle, drawable) in triangles.into_iter() {
+            let dw = drawable.get_width() as i32;
+            let dh = drawable.get_height() as i32;
+            if dw == 0 || dh == 0 {
+                continue;
+            }
+            let drawable_buffer = drawable.get_buffer();
+            self.clip_and_rasterize(triangle, |pix, u, v| {
+                let u = (u.floor() as i32).rem_euclid(dw) as usize;
+                let v = (v.floor() as i32).rem_euclid(dh) as usize;
+                drawable.blend_function(pix, &drawable_buffer[v * dw as usize + u]);
+            });
+        }
+    }
+
+    /// plot receives a destination pixel and perspective-correct uv
+    pub(crate) fn clip_and_rasterize(&mut self, triangle: [PerspectiveVertex; 3], mut plot: impl FnMut(&mut T, f32, f32)) {
+        // Sutherland-Hodgman against the near plane (z = 0 in clip space),
+        // a triangle turns into a polygon of at most 4 vertices
+        let mut clipped = [triangle[0]; 4];
+        let mut clipped_count = 0;
+        for i in 0..3 {
+            let current = triangle[i];
+            let next = triangle[(i + 1) % 3];
+            let current_inside = current.position.z >= 0.0;
+            let next_inside = next.position.z >= 0.0;
+            if current_inside {
+                clipped[clipped_count] = current;
+                clipped_count += 1;
+            }
+            if current_inside != next_inside {
+                let t = current.position.z / (current.position.z - next.position.z);
+                clipped[clipped_count] = PerspectiveVertex {
+                    position: current.position.lerp(next.position, t),
+                    uv: (
+                        current.uv.0 + (next.uv.0 - current.uv.0) * t,
+                        current.uv.1 + (next.uv.1 - current.uv.1) * t
+                    )
+                };
+                clipped_count += 1;
+            }
+        }
+        if clipped_count < 3 {
+            return;
+        }
+
+        let (width, height) = (self.buffer_width as f32, self.buffer_height as f32);
+        let mut screen = [ScreenVertex { x: 0.0, y: 0.0, attributes: Vec4::ZERO }; 4];
+        for (dst, src) in screen.iter_mut().zip(clipped[..clipped_count].iter()) {
+            let inv_w = 1.0 / src.position.w.max(f32::EPSILON);
+            *dst = ScreenVertex {
+                x: (src.position.x * inv_w + 1.0) * 0.5 * width,
+                y: (1.0 - src.position.y * inv_w) * 0.5 * height,
+                attributes: vec4(
+                    src.position.z * inv_w,
+                    inv_w,
+                    src.uv.0 * inv_w,
+                    src.uv.1 * inv_w
+                )
+            };
+        }
+
+        for i in 1..clipped_count - 1 {
+            self.rasterize_screen_triangle([screen[0], screen[i], screen[i + 1]], &mut plot);
+        }
+    }
+
+    fn rasterize_screen_triangle(&mut self, triangle: [ScreenVertex; 3], plot: &mut impl FnMut(&mut T, f32, f32)) {
+        let [a, b, c] = triangle;
+        let area = (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+
+        // attributes are planar, so their gradients are constant over the triangle
+        let (dab, dac) = (b.attributes - a.attributes, c.attributes - a.attributes);
+        let d_dx = (dab * (c.y - a.y) - dac * (b.y - a.y)) / area;
+        let d_dy = (dac * (b.x - a.x) - dab * (c.x - a.x)) / area;
+
+        let [top, middle, bottom] = {
+            let mut sorted = triangle;
+            sorted.sort_by(|l, r| l.y.partial_cmp(&r.y).unwrap_or(std::cmp::Ordering::Equal));
+            sorted
+        };
+
+        let edge_x = |from: ScreenVertex, to: ScreenVertex, y: f32| {
+            if (to.y - from.y).abs() < f32::EPSILON {
+                from.x
+            } else {
+                from.x + (to.x - from.x) * (y - from.y) / (to.y - from.y)
+            }
+        };
+
+        let y_start = top.y.ceil().max(0.0) as usize;
+        let y_end = (bottom.y.ceil().max(0.0) as usize).min(self.buffer_height);
+
+        for y in y_start..y_end {
+            let yf = y as f32;
+            let long_x = edge_x(top, bottom, yf);
+            let short_x = if yf < middle.y {
+                edge_x(top, middle, yf)
+            } else {
+                edge_x(middle, bottom, yf)
+            };
+            let (left, right) = if long_x < short_x { (long_x, short_x) } else { (short_x, long_x) };
+
+            let x_start = left.ceil().max(0.0) as usize;
+            let x_end = (right.ceil().max(0.0) as usize).min(self.buffer_width);
+            if x_start >= x_end {
+                continue;
+            }
+
+            let stride = y * self.buffer_width;
+            let mut attributes = a.attributes +
+                d_dx * (x_start as f32 - a.x) +
+                d_dy * (yf - a.y);
+
+            for idx in stride + x_start..stride + x_end {
+                if let Some(depth_buffer) = self.depth_buffer.as_mut() {
+                    let depth = (attributes.x.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+                    if depth >= depth_buffer[idx] {
+                        attributes += d_dx;
+                        continue;
+                    }
+                    depth_buffer[idx] = depth;
+                }
+                let w = 1.0 / attributes.y;
+                plot(&mut self.buffer[idx], attributes.z * w, attributes.w * w);
+                attributes += d_dx;
+            }
+        }
+    }
+}
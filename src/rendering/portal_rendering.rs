@@ -0,0 +1,254 @@
+use std::collections::{HashMap, VecDeque};
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+
+// safety net against maps with portal cycles that keep the queue busy
+const MAX_QUEUE_ENTRIES: usize = 1024;
+
+/// A convex-ish polygonal sector. Walls are indices into PortalMap::points in clockwise order
+/// (seen from above with y going up), so walls face into the sector.
+/// Wall i goes from walls[i] to walls[(i + 1) % walls.len()]
+#[derive(Clone, Debug)]
+pub struct Sector {
+    pub floor_height: f32,
+    pub ceiling_height: f32,
+    pub walls: Vec<usize>,
+    /// wall index -> index of a neighbouring sector
+    pub portals: HashMap<usize, usize>,
+    pub floor_color: u8,
+    pub ceiling_color: u8,
+    /// index into textures given to PortalRenderer
+    pub wall_texture: usize
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PortalMap {
+    pub points: Vec<(f32, f32)>,
+    pub sectors: Vec<Sector>
+}
+
+impl PortalMap {
+    /// finds a sector containing the point with a crossing number test
+    pub fn find_sector(&self, x: f32, y: f32) -> Option<usize> {
+        self.sectors.iter().position(|sector| {
+            let n = sector.walls.len();
+            let mut inside = false;
+            for i in 0..n {
+                let (x0, y0) = self.points[sector.walls[i]];
+                let (x1, y1) = self.points[sector.walls[(i + 1) % n]];
+                if (y0 > y) != (y1 > y) && x < x0 + (x1 - x0) * (y - y0) / (y1 - y0) {
+                    inside = !inside;
+                }
+            }
+            inside
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PortalCamera {
+    pub position: (f32, f32),
+    pub eye_height: f32,
+    /// in radians, 0 looks along +x, grows counter-clockwise
+    pub angle: f32,
+    /// horizontal field of view in radians
+    pub fov: f32,
+    /// index of a sector the camera is in, see PortalMap::find_sector
+    pub sector: usize
+}
+
+struct QueueEntry {
+    sector: usize,
+    x_left: usize,
+    x_right: usize
+}
+
+/// First person sector/portal renderer in the spirit of the Build engine.
+/// Sectors are drawn front to back, each portal narrows per-column clip windows
+/// for the sector behind it, so every pixel is drawn only once
+pub struct PortalRenderer<'a> {
+    map: &'a PortalMap,
+    textures: &'a [BlittableSurface],
+    texels_per_unit: f32,
+    near: f32
+}
+
+impl<'a> PortalRenderer<'a> {
+    pub fn create(map: &'a PortalMap, textures: &'a [BlittableSurface]) -> Self {
+        Self {
+            map,
+            textures,
+            texels_per_unit: 16.0,
+            near: 0.05
+        }
+    }
+
+    /// how many texels of a wall texture span one map unit. It is 16 by default
+    pub fn with_texels_per_unit(self, texels_per_unit: f32) -> Self {
+        Self { texels_per_unit, ..self }
+    }
+
+    pub fn draw(&self, destination: &mut (impl BufferProviderMut<u8> + SizedSurface), camera: &PortalCamera) {
+        let width = destination.get_width();
+        let height = destination.get_height();
+        if width == 0 || height == 0 || camera.sector >= self.map.sectors.len() {
+            return;
+        }
+        let buffer = destination.get_buffer_mut();
+
+        let half_width = width as f32 / 2.0;
+        let horizon = height as f32 / 2.0;
+        let focal = half_width / (camera.fov / 2.0).tan();
+        let (sin, cos) = camera.angle.sin_cos();
+
+        // visible rows of each column are y_top..y_bottom
+        let mut y_top = vec![0i32; width];
+        let mut y_bottom = vec![height as i32; width];
+
+        let mut queue = VecDeque::new();
+        queue.push_back(QueueEntry { sector: camera.sector, x_left: 0, x_right: width });
+        let mut processed = 0;
+
+        while let Some(QueueEntry { sector: sector_idx, x_left, x_right }) = queue.pop_front() {
+            processed += 1;
+            if processed > MAX_QUEUE_ENTRIES {
+                break;
+            }
+            let sector = &self.map.sectors[sector_idx];
+            let texture = self.textures.get(sector.wall_texture);
+            let n = sector.walls.len();
+
+            for wall_idx in 0..n {
+                let p0 = self.map.points[sector.walls[wall_idx]];
+                let p1 = self.map.points[sector.walls[(wall_idx + 1) % n]];
+                let wall_length = ((p1.0 - p0.0).powi(2) + (p1.1 - p0.1).powi(2)).sqrt();
+
+                // to view space: r goes to the right, z goes forward
+                let to_view = |p: (f32, f32)| {
+                    let (dx, dy) = (p.0 - camera.position.0, p.1 - camera.position.1);
+                    (dx * sin - dy * cos, dx * cos + dy * sin)
+                };
+                let (mut r0, mut z0) = to_view(p0);
+                let (mut r1, mut z1) = to_view(p1);
+                let (mut u0, mut u1) = (0.0, wall_length * self.texels_per_unit);
+
+                if z0 < self.near && z1 < self.near {
+                    continue;
+                }
+                if z0 < self.near {
+                    let t = (self.near - z0) / (z1 - z0);
+                    r0 += (r1 - r0) * t;
+                    u0 += (u1 - u0) * t;
+                    z0 = self.near;
+                } else if z1 < self.near {
+                    let t = (self.near - z0) / (z1 - z0);
+                    r1 = r0 + (r1 - r0) * t;
+                    u1 = u0 + (u1 - u0) * t;
+                    z1 = self.near;
+                }
+
+                let screen_x0 = half_width + r0 * focal / z0;
+                let screen_x1 = half_width + r1 * focal / z1;
+                if screen_x0 >= screen_x1 {
+                    // we see the back side of the wall
+                    continue;
+                }
+                let x_start = (screen_x0.ceil().max(0.0) as usize).max(x_left);
+                let x_end = (screen_x1.ceil().max(0.0) as usize).min(x_right);
+                if x_start >= x_end {
+                    continue;
+                }
+
+                let neighbour = sector.portals
+                    .get(&wall_idx)
+                    .and_then(|it| self.map.sectors.get(*it).map(|sector| (*it, sector)));
+
+                let (inv_z0, inv_z1) = (1.0 / z0, 1.0 / z1);
+                for x in x_start..x_end {
+                    // 1/z and u/z are linear in screen space
+                    let t = (x as f32 - screen_x0) / (screen_x1 - screen_x0);
+                    let inv_z = inv_z0 + (inv_z1 - inv_z0) * t;
+                    let u = (u0 * inv_z0 + (u1 * inv_z1 - u0 * inv_z0) * t) / inv_z;
+                    let scale = focal * inv_z;
+
+                    let (top, bottom) = (y_top[x], y_bottom[x]);
+                    let project = |world_height: f32| {
+                        ((horizon - (world_height - camera.eye_height) * scale).ceil() as i32).clamp(top, bottom)
+                    };
+                    let ceiling_y = project(sector.ceiling_height);
+                    let floor_y = project(sector.floor_height);
+
+                    fill_column(buffer, width, x, top, ceiling_y, sector.ceiling_color);
+                    fill_column(buffer, width, x, floor_y, bottom, sector.floor_color);
+
+                    let column = WallColumn {
+                        x,
+                        u,
+                        texel_per_row: self.texels_per_unit / scale,
+                        top_v: (sector.ceiling_height - camera.eye_height - horizon / scale) * self.texels_per_unit
+                    };
+
+                    match neighbour {
+                        Some((_, next_sector)) => {
+                            let next_ceiling_y = project(next_sector.ceiling_height).max(ceiling_y);
+                            let next_floor_y = project(next_sector.floor_height).min(floor_y);
+                            // upper and lower steps between sectors
+                            self.draw_wall_column(buffer, width, texture, &column, ceiling_y, next_ceiling_y);
+                            self.draw_wall_column(buffer, width, texture, &column, next_floor_y, floor_y);
+                            y_top[x] = next_ceiling_y;
+                            y_bottom[x] = next_floor_y.max(next_ceiling_y);
+                        },
+                        None => {
+                            self.draw_wall_column(buffer, width, texture, &column, ceiling_y, floor_y);
+                            y_top[x] = bottom;
+                        }
+                    }
+                }
+
+                if let Some((next_idx, _)) = neighbour {
+                    queue.push_back(QueueEntry {
+                        sector: next_idx,
+                        x_left: x_start,
+                        x_right: x_end
+                    });
+                }
+            }
+        }
+    }
+
+    fn draw_wall_column(
+        &self,
+        buffer: &mut [u8], buffer_width: usize,
+        texture: Option<&BlittableSurface>,
+        column: &WallColumn,
+        y_from: i32, y_to: i32
+    ) {
+        let texture = match texture {
+            Some(texture) if texture.get_width() > 0 && texture.get_height() > 0 => texture,
+            _ => return
+        };
+        let (tw, th) = (texture.get_width() as i32, texture.get_height() as i32);
+        let texture_buffer = texture.get_buffer();
+        let tu = (column.u.floor() as i32).rem_euclid(tw) as usize;
+
+        // v is measured down from the sector ceiling
+        for y in y_from.max(0)..y_to {
+            let v = column.top_v + y as f32 * column.texel_per_row;
+            let tv = (v.floor() as i32).rem_euclid(th) as usize;
+            buffer[y as usize * buffer_width + column.x] = texture_buffer[tv * tw as usize + tu];
+        }
+    }
+}
+
+struct WallColumn {
+    x: usize,
+    u: f32,
+    texel_per_row: f32,
+    top_v: f32
+}
+
+fn fill_column(buffer: &mut [u8], buffer_width: usize, x: usize, y_from: i32, y_to: i32, color: u8) {
+    for y in y_from.max(0)..y_to {
+        buffer[y as usize * buffer_width + x] = color;
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use crate::rendering::blittable::{BlitBuilder, Blittable, BufferProviderMut, Flip, SizedSurface};
+use crate::rendering::bresenham::LineStripRasterizer;
+use crate::rendering::deformed_rendering::{TexturedVertex, TriangleRasterizer, Vertex};
+use crate::rendering::transform::Transform;
+
+const DEFAULT_BAND_HEIGHT: usize = 32;
+
+pub enum RasterCommand<'a, T: Copy> {
+    Triangles {
+        color: T,
+        vertices: &'a [Vertex],
+        indices: &'a [u16],
+        transform: Transform
+    },
+    TexturedTriangles {
+        drawable: &'a (dyn Blittable<T> + Sync),
+        vertices: &'a [TexturedVertex],
+        indices: &'a [u16],
+        transform: Transform
+    },
+    LineStrip {
+        color: T,
+        positions: &'a [(i16, i16)],
+        closed: bool,
+        transform: Transform
+    },
+    Blit {
+        drawable: &'a (dyn Blittable<T> + Sync),
+        position: (i16, i16),
+        flip: Flip
+    }
+}
+
+/// A batch of drawing commands. The destination is split into horizontal bands and every band
+/// runs all commands in order, clipped to its rows. With the "parallel" feature bands are rasterized
+/// on the rayon thread pool, the result is the same as of the serial path either way
+pub struct RasterBatch<'a, T: Copy> {
+    commands: Vec<RasterCommand<'a, T>>,
+    band_height: usize
+}
+
+impl<'a, T: Copy> Default for RasterBatch<'a, T> {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            band_height: DEFAULT_BAND_HEIGHT
+        }
+    }
+}
+
+impl<'a, T: Copy + Default + Send + Sync> RasterBatch<'a, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// rows per band. Smaller bands balance better between threads,
+    /// but every band walks through all commands. It is 32 by default
+    pub fn with_band_height(self, band_height: usize) -> Self {
+        Self { band_height: band_height.max(1), ..self }
+    }
+
+    pub fn push(&mut self, command: RasterCommand<'a, T>) {
+        self.commands.push(command);
+    }
+
+    pub fn push_triangles(&mut self, color: T, vertices: &'a [Vertex], indices: &'a [u16], transform: Transform) {
+        self.push(RasterCommand::Triangles { color, vertices, indices, transform });
+    }
+
+    pub fn push_textured_triangles(
+        &mut self,
+        drawable: &'a (dyn Blittable<T> + Sync),
+        vertices: &'a [TexturedVertex],
+        indices: &'a [u16],
+        transform: Transform
+    ) {
+        self.push(RasterCommand::TexturedTriangles { drawable, vertices, indices, transform });
+    }
+
+    pub fn push_line_strip(&mut self, color: T, positions: &'a [(i16, i16)], closed: bool, transform: Transform) {
+        self.push(RasterCommand::LineStrip { color, positions, closed, transform });
+    }
+
+    pub fn push_blit(&mut self, drawable: &'a (dyn Blittable<T> + Sync), position: (i16, i16), flip: Flip) {
+        self.push(RasterCommand::Blit { drawable, position, flip });
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// rasterizes by bands, in parallel when the "parallel" feature is enabled
+    pub fn rasterize(&self, dest: &mut (impl BufferProviderMut<T> + SizedSurface)) {
+        let width = dest.get_width();
+        if width == 0 {
+            return;
+        }
+        let band_height = self.band_height;
+        let buffer = dest.get_buffer_mut();
+
+        #[cfg(feature = "parallel")]
+        buffer.par_chunks_mut(band_height * width)
+            .enumerate()
+            .for_each(|(band_idx, band)| self.rasterize_band(band, width, band_idx * band_height));
+
+        #[cfg(not(feature = "parallel"))]
+        for (band_idx, band) in buffer.chunks_mut(band_height * width).enumerate() {
+            self.rasterize_band(band, width, band_idx * band_height);
+        }
+    }
+
+    /// rasterizes the whole destination at once on the current thread
+    pub fn rasterize_serial(&self, dest: &mut (impl BufferProviderMut<T> + SizedSurface)) {
+        let width = dest.get_width();
+        if width == 0 {
+            return;
+        }
+        self.rasterize_band(dest.get_buffer_mut(), width, 0);
+    }
+
+    fn rasterize_band(&self, band: &mut [T], width: usize, band_top: usize) {
+        for command in self.commands.iter() {
+            match *command {
+                RasterCommand::Triangles { color, vertices, indices, transform } => {
+                    TriangleRasterizer::create_band(band, width, band_top)
+                        .with_transform(transform)
+                        .rasterize_with_color(color, vertices, indices);
+                },
+                RasterCommand::TexturedTriangles { drawable, vertices, indices, transform } => {
+                    TriangleRasterizer::create_band(band, width, band_top)
+                        .with_transform(transform)
+                        .rasterize_with_surface(drawable, vertices, indices);
+                },
+                RasterCommand::LineStrip { color, positions, closed, transform } => {
+                    let rasterizer = LineStripRasterizer::create_band(band, width, band_top)
+                        .with_color(color)
+                        .with_transform(transform);
+                    if closed {
+                        rasterizer.closed().rasterize_slice(positions);
+                    } else {
+                        rasterizer.rasterize_slice(positions);
+                    }
+                },
+                RasterCommand::Blit { drawable, position, flip } => {
+                    let band_y = position.1 as i32 - band_top as i32;
+                    let band_height = (band.len() / width) as i32;
+                    if band_y >= band_height || band_y + (drawable.get_height() as i32) <= 0 {
+                        continue;
+                    }
+                    // the dest subrect is sized by the drawable, so bands clip it only by their own rows
+                    BlitBuilder::create_ext(band, width, drawable)
+                        .with_dest_subrect(position.0, band_y as i16, drawable.get_width(), drawable.get_height())
+                        .with_flip(flip)
+                        .blit();
+                }
+            }
+        }
+    }
+}
+
+/// Runs a full screen effect row by row, in parallel when the "parallel" feature is enabled.
+/// The effect gets the row index and the row itself
+pub fn process_rows<T: Copy + Send>(
+    dest: &mut (impl BufferProviderMut<T> + SizedSurface),
+    effect: impl Fn(usize, &mut [T]) + Send + Sync
+) {
+    let width = dest.get_width();
+    if width == 0 {
+        return;
+    }
+    let buffer = dest.get_buffer_mut();
+
+    #[cfg(feature = "parallel")]
+    buffer.par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(y, row)| effect(y, row));
+
+    #[cfg(not(feature = "parallel"))]
+    for (y, row) in buffer.chunks_mut(width).enumerate() {
+        effect(y, row);
+    }
+}
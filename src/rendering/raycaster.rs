@@ -0,0 +1,373 @@
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+use crate::rendering::shade_table::ShadeTable;
+
+// rays are stopped after this many cells even if the grid is open
+const MAX_RAY_STEPS: usize = 256;
+
+/// A grid of cells where 0 is an empty cell and n is a wall with textures[n - 1].
+/// Cells outside of the grid are treated as empty
+pub struct RaycastGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>
+}
+
+impl RaycastGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![0; width * height]
+        }
+    }
+
+    pub fn from_cells(width: usize, height: usize, cells: Vec<u8>) -> Self {
+        let mut cells = cells;
+        cells.resize(width * height, 0);
+        Self { width, height, cells }
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_cell(&self, x: i32, y: i32) -> u8 {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return 0;
+        }
+        self.cells[y as usize * self.width + x as usize]
+    }
+
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: u8) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+}
+
+/// Grid units, y grows downwards like rows of the grid.
+/// angle is in radians, 0 looks along +x
+#[derive(Copy, Clone, Debug)]
+pub struct RaycastCamera {
+    pub position: (f32, f32),
+    pub angle: f32,
+    /// horizontal field of view in radians
+    pub fov: f32
+}
+
+/// Billboard standing on the floor
+#[derive(Copy, Clone, Debug)]
+pub struct RaycastSprite {
+    pub position: (f32, f32),
+    /// index into the textures given to Raycaster
+    pub texture: usize,
+    /// height in grid units, 1.0 is the height of walls
+    pub scale: f32
+}
+
+#[derive(Copy, Clone)]
+pub enum PlaneFill {
+    Color(u8),
+    /// index into the textures given to Raycaster, repeated once per cell
+    Texture(usize)
+}
+
+struct View {
+    width: usize,
+    height: usize,
+    horizon: f32,
+    focal: f32,
+    dir: (f32, f32),
+    right: (f32, f32)
+}
+
+impl View {
+    fn new(width: usize, height: usize, camera: &RaycastCamera) -> Self {
+        let (sin, cos) = camera.angle.sin_cos();
+        Self {
+            width,
+            height,
+            horizon: height as f32 / 2.0,
+            focal: width as f32 / 2.0 / (camera.fov / 2.0).tan(),
+            dir: (cos, sin),
+            right: (-sin, cos)
+        }
+    }
+}
+
+/// DDA grid raycaster in the spirit of Wolfenstein 3D.
+/// After drawing walls it keeps a per-column depth buffer, so sprites drawn afterwards
+/// are hidden behind walls
+pub struct Raycaster<'a> {
+    textures: &'a [BlittableSurface],
+    shade_table: Option<&'a ShadeTable>,
+    shade_distance: f32,
+    floor: PlaneFill,
+    ceiling: PlaneFill,
+    sprite_color_key: u8,
+    depth_buffer: Vec<f32>
+}
+
+impl<'a> Raycaster<'a> {
+    pub fn new(textures: &'a [BlittableSurface]) -> Self {
+        Self {
+            textures,
+            shade_table: None,
+            shade_distance: 16.0,
+            floor: PlaneFill::Color(0),
+            ceiling: PlaneFill::Color(0),
+            sprite_color_key: 0,
+            depth_buffer: Vec::new()
+        }
+    }
+
+    /// pixels are shaded proportionally to their distance,
+    /// reaching the last level of the table at shade_distance
+    pub fn with_shade_table(self, shade_table: &'a ShadeTable, shade_distance: f32) -> Self {
+        Self { shade_table: Some(shade_table), shade_distance, ..self }
+    }
+
+    pub fn with_floor(self, floor: PlaneFill) -> Self {
+        Self { floor, ..self }
+    }
+
+    pub fn with_ceiling(self, ceiling: PlaneFill) -> Self {
+        Self { ceiling, ..self }
+    }
+
+    /// transparent color of sprite textures. It is 0 by default
+    pub fn with_sprite_color_key(self, sprite_color_key: u8) -> Self {
+        Self { sprite_color_key, ..self }
+    }
+
+    /// perpendicular wall distance for each column of the last draw call
+    pub fn get_depth_buffer(&self) -> &[f32] {
+        &self.depth_buffer
+    }
+
+    pub fn draw(&mut self, destination: &mut (impl BufferProviderMut<u8> + SizedSurface), grid: &RaycastGrid, camera: &RaycastCamera) {
+        let view = View::new(destination.get_width(), destination.get_height(), camera);
+        let buffer = destination.get_buffer_mut();
+        self.depth_buffer.clear();
+        self.depth_buffer.resize(view.width, f32::MAX);
+
+        self.draw_planes(buffer, &view, camera);
+
+        for x in 0..view.width {
+            let camera_x = (x as f32 + 0.5 - view.width as f32 / 2.0) / view.focal;
+            let ray = (
+                view.dir.0 + view.right.0 * camera_x,
+                view.dir.1 + view.right.1 * camera_x
+            );
+            let hit = match cast_ray(grid, camera.position, ray) {
+                None => continue,
+                Some(hit) => hit
+            };
+            self.depth_buffer[x] = hit.distance;
+
+            let texture = match self.textures.get(hit.cell as usize - 1) {
+                Some(texture) if texture.get_width() > 0 && texture.get_height() > 0 => texture,
+                _ => continue
+            };
+            let (tw, th) = (texture.get_width(), texture.get_height());
+            let texture_buffer = texture.get_buffer();
+
+            let mut tx = ((hit.wall_x * tw as f32) as usize).min(tw - 1);
+            if (!hit.y_side && ray.0 < 0.0) || (hit.y_side && ray.1 > 0.0) {
+                tx = tw - tx - 1;
+            }
+
+            let line_height = view.focal / hit.distance;
+            let line_top = view.horizon - line_height / 2.0;
+            let y_start = line_top.ceil().max(0.0) as usize;
+            let y_end = ((view.horizon + line_height / 2.0).ceil().max(0.0) as usize).min(view.height);
+
+            // y sides are a bit darker to tell walls apart
+            let shade = self.shade_level(hit.distance) + if hit.y_side { 1 } else { 0 };
+            let texel_step = th as f32 / line_height;
+            let mut tv = (y_start as f32 - line_top) * texel_step;
+            for y in y_start..y_end {
+                let color = texture_buffer[(tv as usize).min(th - 1) * tw + tx];
+                buffer[y * view.width + x] = self.shade(color, shade);
+                tv += texel_step;
+            }
+        }
+    }
+
+    /// sprites are sorted back to front and clipped by the depth buffer of the last draw call
+    pub fn draw_sprites(
+        &self,
+        destination: &mut (impl BufferProviderMut<u8> + SizedSurface),
+        camera: &RaycastCamera,
+        sprites: &[RaycastSprite]
+    ) {
+        let view = View::new(destination.get_width(), destination.get_height(), camera);
+        let buffer = destination.get_buffer_mut();
+
+        let mut projected = sprites
+            .iter()
+            .filter_map(|sprite| {
+                let rel = (sprite.position.0 - camera.position.0, sprite.position.1 - camera.position.1);
+                let depth = rel.0 * view.dir.0 + rel.1 * view.dir.1;
+                if depth <= f32::EPSILON {
+                    return None;
+                }
+                let side = rel.0 * view.right.0 + rel.1 * view.right.1;
+                Some((sprite, depth, view.width as f32 / 2.0 + side * view.focal / depth))
+            })
+            .collect::<Vec<_>>();
+        projected.sort_by(|l, r| r.1.partial_cmp(&l.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (sprite, depth, screen_x) in projected {
+            let texture = match self.textures.get(sprite.texture) {
+                Some(texture) if texture.get_width() > 0 && texture.get_height() > 0 => texture,
+                _ => continue
+            };
+            let (tw, th) = (texture.get_width(), texture.get_height());
+            let texture_buffer = texture.get_buffer();
+
+            let sprite_height = view.focal * sprite.scale / depth;
+            let sprite_width = sprite_height * tw as f32 / th as f32;
+            let bottom = view.horizon + view.focal * 0.5 / depth;
+            let (left, top) = (screen_x - sprite_width / 2.0, bottom - sprite_height);
+
+            let x_start = left.ceil().max(0.0) as usize;
+            let x_end = ((left + sprite_width).ceil().max(0.0) as usize).min(view.width);
+            let y_start = top.ceil().max(0.0) as usize;
+            let y_end = (bottom.ceil().max(0.0) as usize).min(view.height);
+            let shade = self.shade_level(depth);
+
+            for x in x_start..x_end {
+                if self.depth_buffer.get(x).map(|it| *it <= depth).unwrap_or(false) {
+                    continue;
+                }
+                let tx = (((x as f32 - left) * tw as f32 / sprite_width) as usize).min(tw - 1);
+                for y in y_start..y_end {
+                    let ty = (((y as f32 - top) * th as f32 / sprite_height) as usize).min(th - 1);
+                    let color = texture_buffer[ty * tw + tx];
+                    if color != self.sprite_color_key {
+                        buffer[y * view.width + x] = self.shade(color, shade);
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_planes(&self, buffer: &mut [u8], view: &View, camera: &RaycastCamera) {
+        // the eye is half way between the floor and the ceiling
+        for y in 0..view.height {
+            let rows_from_horizon = y as f32 + 0.5 - view.horizon;
+            let (fill, rows_from_horizon) = if rows_from_horizon > 0.0 {
+                (self.floor, rows_from_horizon)
+            } else {
+                (self.ceiling, -rows_from_horizon)
+            };
+            let row = &mut buffer[y * view.width..(y + 1) * view.width];
+            let row_distance = 0.5 * view.focal / rows_from_horizon;
+            let shade = self.shade_level(row_distance);
+
+            let texture = match fill {
+                PlaneFill::Color(color) => {
+                    row.fill(self.shade(color, shade));
+                    continue;
+                },
+                PlaneFill::Texture(idx) => match self.textures.get(idx) {
+                    Some(texture) if texture.get_width() > 0 && texture.get_height() > 0 => texture,
+                    _ => continue
+                }
+            };
+            let (tw, th) = (texture.get_width(), texture.get_height());
+            let texture_buffer = texture.get_buffer();
+
+            let camera_x = (0.5 - view.width as f32 / 2.0) / view.focal;
+            let mut world_x = camera.position.0 + row_distance * (view.dir.0 + view.right.0 * camera_x);
+            let mut world_y = camera.position.1 + row_distance * (view.dir.1 + view.right.1 * camera_x);
+            let step_x = row_distance * view.right.0 / view.focal;
+            let step_y = row_distance * view.right.1 / view.focal;
+
+            for pix in row.iter_mut() {
+                let tx = ((world_x - world_x.floor()) * tw as f32) as usize;
+                let ty = ((world_y - world_y.floor()) * th as f32) as usize;
+                let color = texture_buffer[ty.min(th - 1) * tw + tx.min(tw - 1)];
+                *pix = self.shade(color, shade);
+                world_x += step_x;
+                world_y += step_y;
+            }
+        }
+    }
+
+    fn shade_level(&self, distance: f32) -> usize {
+        match self.shade_table {
+            None => 0,
+            Some(table) => {
+                let levels = table.level_count();
+                ((distance / self.shade_distance * levels as f32) as usize).min(levels - 1)
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn shade(&self, color: u8, level: usize) -> u8 {
+        match self.shade_table {
+            None => color,
+            Some(table) => table.shade(color, level)
+        }
+    }
+}
+
+struct RayHit {
+    cell: u8,
+    distance: f32,
+    // where exactly the wall was hit, 0..1
+    wall_x: f32,
+    y_side: bool
+}
+
+fn cast_ray(grid: &RaycastGrid, position: (f32, f32), ray: (f32, f32)) -> Option<RayHit> {
+    let (mut map_x, mut map_y) = (position.0.floor() as i32, position.1.floor() as i32);
+    let delta_x = if ray.0 == 0.0 { f32::MAX } else { (1.0 / ray.0).abs() };
+    let delta_y = if ray.1 == 0.0 { f32::MAX } else { (1.0 / ray.1).abs() };
+    let (step_x, mut side_x) = if ray.0 < 0.0 {
+        (-1, (position.0 - map_x as f32) * delta_x)
+    } else {
+        (1, (map_x as f32 + 1.0 - position.0) * delta_x)
+    };
+    let (step_y, mut side_y) = if ray.1 < 0.0 {
+        (-1, (position.1 - map_y as f32) * delta_y)
+    } else {
+        (1, (map_y as f32 + 1.0 - position.1) * delta_y)
+    };
+
+    for _ in 0..MAX_RAY_STEPS {
+        let y_side = if side_x < side_y {
+            side_x += delta_x;
+            map_x += step_x;
+            false
+        } else {
+            side_y += delta_y;
+            map_y += step_y;
+            true
+        };
+        let cell = grid.get_cell(map_x, map_y);
+        if cell != 0 {
+            let distance = if y_side { side_y - delta_y } else { side_x - delta_x };
+            let wall_x = if y_side {
+                position.0 + distance * ray.0
+            } else {
+                position.1 + distance * ray.1
+            };
+            return Some(RayHit {
+                cell,
+                distance: distance.max(f32::EPSILON),
+                wall_x: wall_x - wall_x.floor(),
+                y_side
+            });
+        }
+    }
+    None
+}
@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, Rect, SizedSurface};
+
+#[derive(Copy, Clone, Debug)]
+pub enum Connectivity {
+    /// only horizontal and vertical neighbours are connected
+    Four,
+    /// diagonal neighbours are connected too
+    Eight
+}
+
+/// Which palette indices belong to a flood filled region
+#[derive(Clone, Debug)]
+pub enum ColorMatch {
+    /// the color of the start pixel
+    Exact,
+    /// colors within the tolerance from the color of the start pixel
+    Tolerance(u8),
+    Range(RangeInclusive<u8>)
+}
+
+impl ColorMatch {
+    fn to_range(&self, start_color: u8) -> RangeInclusive<u8> {
+        match self {
+            ColorMatch::Exact => start_color..=start_color,
+            ColorMatch::Tolerance(tolerance) =>
+                start_color.saturating_sub(*tolerance)..=start_color.saturating_add(*tolerance),
+            ColorMatch::Range(range) => range.clone()
+        }
+    }
+}
+
+struct BoundsAccumulator {
+    min: (usize, usize),
+    max: (usize, usize)
+}
+
+impl BoundsAccumulator {
+    fn new() -> Self {
+        Self {
+            min: (usize::MAX, usize::MAX),
+            max: (0, 0)
+        }
+    }
+
+    fn add_span(&mut self, y: usize, x0: usize, x1: usize) {
+        self.min = (self.min.0.min(x0), self.min.1.min(y));
+        self.max = (self.max.0.max(x1), self.max.1.max(y));
+    }
+
+    fn to_rect(&self) -> Option<Rect> {
+        if self.min.0 > self.max.0 {
+            return None;
+        }
+        Some(Rect {
+            x_range: self.min.0..self.max.0 + 1,
+            y_range: self.min.1..self.max.1 + 1
+        })
+    }
+}
+
+/// Scanline flood fill. Returns the bounds of the filled region,
+/// or None if the start pixel is outside the surface or doesn't match
+pub fn flood_fill(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    start: (i16, i16),
+    color: u8,
+    connectivity: Connectivity,
+    color_match: ColorMatch
+) -> Option<Rect> {
+    let (width, height) = (dest.get_width(), dest.get_height());
+    if start.0 < 0 || start.1 < 0 || start.0 as usize >= width || start.1 as usize >= height {
+        return None;
+    }
+    let buffer = dest.get_buffer_mut();
+    let start = (start.0 as usize, start.1 as usize);
+    let range = color_match.to_range(buffer[start.1 * width + start.0]);
+
+    // the fill color may match too, so filled pixels are tracked separately
+    let mut visited = vec![false; width * height];
+    let mut bounds = BoundsAccumulator::new();
+    let mut seeds = vec![start];
+    let diagonal = match connectivity {
+        Connectivity::Four => 0,
+        Connectivity::Eight => 1
+    };
+
+    while let Some((x, y)) = seeds.pop() {
+        let stride = y * width;
+        if visited[stride + x] || !range.contains(&buffer[stride + x]) {
+            continue;
+        }
+        let mut left = x;
+        while left > 0 && !visited[stride + left - 1] && range.contains(&buffer[stride + left - 1]) {
+            left -= 1;
+        }
+        let mut right = x;
+        while right + 1 < width && !visited[stride + right + 1] && range.contains(&buffer[stride + right + 1]) {
+            right += 1;
+        }
+        for px in left..=right {
+            buffer[stride + px] = color;
+            visited[stride + px] = true;
+        }
+        bounds.add_span(y, left, right);
+
+        let scan_left = left.saturating_sub(diagonal);
+        let scan_right = (right + diagonal).min(width - 1);
+        let neighbour_rows = [y.checked_sub(1), Some(y + 1).filter(|&it| it < height)];
+        for &row in neighbour_rows.iter().flatten() {
+            let row_stride = row * width;
+            let mut in_run = false;
+            for px in scan_left..=scan_right {
+                let matches = !visited[row_stride + px] && range.contains(&buffer[row_stride + px]);
+                if matches && !in_run {
+                    seeds.push((px, row));
+                }
+                in_run = matches;
+            }
+        }
+    }
+
+    bounds.to_rect()
+}
+
+/// Replaces every color from the range. Returns the count of replaced pixels
+pub fn replace_color(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    from: RangeInclusive<u8>,
+    to: u8
+) -> usize {
+    let mut count = 0;
+    for px in dest.get_buffer_mut().iter_mut() {
+        if from.contains(px) {
+            *px = to;
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Bounds of all pixels accepted by the predicate, e.g. everything but the color key
+pub fn bounding_box(
+    source: &(impl BufferProvider<u8> + SizedSurface),
+    predicate: impl Fn(u8) -> bool
+) -> Option<Rect> {
+    let width = source.get_width();
+    if width == 0 {
+        return None;
+    }
+    let mut bounds = BoundsAccumulator::new();
+    for (y, row) in source.get_buffer().chunks_exact(width).enumerate() {
+        let first = row.iter().position(|&it| predicate(it));
+        let last = row.iter().rposition(|&it| predicate(it));
+        if let (Some(first), Some(last)) = (first, last) {
+            bounds.add_span(y, first, last);
+        }
+    }
+    bounds.to_rect()
+}
+
+pub struct Component {
+    pub color: u8,
+    pub pixel_count: usize,
+    pub bounds: Rect
+}
+
+/// Result of connected component labeling. Label 0 is the background,
+/// label n is the component n - 1
+pub struct ComponentLabels {
+    width: usize,
+    height: usize,
+    labels: Vec<u32>,
+    components: Vec<Component>
+}
+
+impl ComponentLabels {
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_label(&self, x: usize, y: usize) -> u32 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.labels[y * self.width + x]
+    }
+
+    pub fn get_labels(&self) -> &[u32] {
+        &self.labels
+    }
+
+    pub fn get_component(&self, label: u32) -> Option<&Component> {
+        if label == 0 {
+            return None;
+        }
+        self.components.get(label as usize - 1)
+    }
+
+    pub fn get_components(&self) -> &[Component] {
+        &self.components
+    }
+}
+
+/// Labels connected regions of the same color. Pixels of the background color are not labeled
+pub fn label_components(
+    source: &(impl BufferProvider<u8> + SizedSurface),
+    connectivity: Connectivity,
+    background: Option<u8>
+) -> ComponentLabels {
+    let (width, height) = (source.get_width(), source.get_height());
+    let buffer = source.get_buffer();
+    let mut labels = vec![0u32; width * height];
+    let mut components = Vec::new();
+    let mut queue = VecDeque::new();
+
+    let neighbours: &[(isize, isize)] = match connectivity {
+        Connectivity::Four => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
+        Connectivity::Eight => &[(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+    };
+
+    for start in 0..width * height {
+        let color = buffer[start];
+        if labels[start] != 0 || Some(color) == background {
+            continue;
+        }
+        let label = components.len() as u32 + 1;
+        let mut bounds = BoundsAccumulator::new();
+        let mut pixel_count = 0;
+        labels[start] = label;
+        queue.push_back(start);
+
+        while let Some(idx) = queue.pop_front() {
+            let (x, y) = (idx % width, idx / width);
+            bounds.add_span(y, x, x);
+            pixel_count += 1;
+            for &(dx, dy) in neighbours.iter() {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= height {
+                    continue;
+                }
+                let neighbour = ny as usize * width + nx as usize;
+                if labels[neighbour] == 0 && buffer[neighbour] == color {
+                    labels[neighbour] = label;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        if let Some(bounds) = bounds.to_rect() {
+            components.push(Component {
+                color,
+                pixel_count,
+                bounds
+            });
+        }
+    }
+
+    ComponentLabels {
+        width,
+        height,
+        labels,
+        components
+    }
+}
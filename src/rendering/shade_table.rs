@@ -0,0 +1,66 @@
+/// Palette lookup table for shading indexed colors. Level 0 keeps colors as they are,
+/// the last level maps every color to the palette entry closest to the target color
+/// (e.g. black for darkness or a fog color)
+pub struct ShadeTable {
+    level_count: usize,
+    table: Vec<u8>
+}
+
+impl ShadeTable {
+    pub fn from_palette(palette: &[[u8; 3]], level_count: usize, target_color: [u8; 3]) -> Self {
+        let level_count = level_count.max(1);
+        let mut table = vec![0u8; level_count * 256];
+        for level in 0..level_count {
+            let t = if level_count > 1 { level as f32 / (level_count - 1) as f32 } else { 0.0 };
+            for color in 0..256 {
+                let table_idx = level * 256 + color;
+                let source = match palette.get(color) {
+                    None => {
+                        table[table_idx] = color as u8;
+                        continue;
+                    },
+                    Some(source) => source
+                };
+                if level == 0 {
+                    table[table_idx] = color as u8;
+                    continue;
+                }
+                let shaded = [0, 1, 2].map(|i| {
+                    source[i] as f32 + (target_color[i] as f32 - source[i] as f32) * t
+                });
+                table[table_idx] = find_closest(palette, shaded);
+            }
+        }
+        Self { level_count, table }
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.level_count
+    }
+
+    #[inline(always)]
+    pub fn shade(&self, color: u8, level: usize) -> u8 {
+        self.table[level.min(self.level_count - 1) * 256 + color as usize]
+    }
+
+    /// a 256 entries slice for a single level
+    pub fn get_level(&self, level: usize) -> &[u8] {
+        let level = level.min(self.level_count - 1);
+        &self.table[level * 256..(level + 1) * 256]
+    }
+}
+
+fn find_closest(palette: &[[u8; 3]], color: [f32; 3]) -> u8 {
+    let mut best = 0;
+    let mut best_distance = f32::MAX;
+    for (idx, entry) in palette.iter().take(256).enumerate() {
+        let distance = (0..3)
+            .map(|i| (entry[i] as f32 - color[i]).powi(2))
+            .sum::<f32>();
+        if distance < best_distance {
+            best_distance = distance;
+            best = idx;
+        }
+    }
+    best as u8
+}
@@ -1,4 +1,5 @@
-use crate::rendering::blittable::{BufferProviderMut, SizedSurface};
+use crate::rendering::blittable::{Blittable, BufferProviderMut, SizedSurface};
+use crate::rendering::bresenham::plot_bresenham_line;
 
 pub fn fill_rectangle(
     dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
@@ -30,4 +31,449 @@ pub fn fill_rectangle(
         }
         stride += dw;
     }
-}
\ No newline at end of file
+}
+
+/// How shapes are painted
+#[derive(Copy, Clone)]
+pub enum ShapeFill<'a> {
+    Color(u8),
+    /// the pattern is tiled over the destination starting from its top left corner,
+    /// its blend function is respected, so color keyed patterns leave holes
+    Pattern(&'a dyn Blittable<u8>)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ThickLineCap {
+    /// the line ends exactly at its end points
+    Butt,
+    /// the line is extended by half of its width
+    Square,
+    Round
+}
+
+struct ShapePainter<'a, 'b> {
+    buffer: &'a mut [u8],
+    width: i32,
+    height: i32,
+    fill: ShapeFill<'b>
+}
+
+impl<'a, 'b> ShapePainter<'a, 'b> {
+    fn new(dest: &'a mut (impl BufferProviderMut<u8> + SizedSurface), fill: ShapeFill<'b>) -> Self {
+        let (width, height) = (dest.get_width() as i32, dest.get_height() as i32);
+        Self {
+            buffer: dest.get_buffer_mut(),
+            width,
+            height,
+            fill
+        }
+    }
+
+    #[inline(always)]
+    fn plot(&mut self, x: i32, y: i32) {
+        if (0..self.width).contains(&x) && (0..self.height).contains(&y) {
+            self.span(y, x, x);
+        }
+    }
+
+    /// x0 and x1 are inclusive
+    fn span(&mut self, y: i32, x0: i32, x1: i32) {
+        if !(0..self.height).contains(&y) {
+            return;
+        }
+        let (x0, x1) = (x0.max(0), x1.min(self.width - 1));
+        if x0 > x1 {
+            return;
+        }
+        let stride = (y * self.width) as usize;
+        let row = &mut self.buffer[stride + x0 as usize..=stride + x1 as usize];
+        match self.fill {
+            ShapeFill::Color(color) => {
+                for px in row.iter_mut() {
+                    *px = color;
+                }
+            },
+            ShapeFill::Pattern(pattern) => {
+                let (pw, ph) = (pattern.get_width(), pattern.get_height());
+                if pw == 0 || ph == 0 {
+                    return;
+                }
+                let pattern_stride = (y as usize % ph) * pw;
+                let pattern_row = &pattern.get_buffer()[pattern_stride..pattern_stride + pw];
+                let mut pattern_x = x0 as usize % pw;
+                for px in row.iter_mut() {
+                    pattern.blend_function(px, &pattern_row[pattern_x]);
+                    pattern_x += 1;
+                    if pattern_x == pw {
+                        pattern_x = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    /// spans of a polygon with pixel centers at +0.5, filled with the even-odd rule
+    fn polygon(&mut self, points: &[(f32, f32)]) {
+        if points.len() < 3 {
+            return;
+        }
+        let (min_y, max_y) = points.iter().fold(
+            (f32::MAX, f32::MIN),
+            |(min_y, max_y), p| (min_y.min(p.1), max_y.max(p.1))
+        );
+        let first_row = ((min_y - 0.5).ceil() as i32).max(0);
+        let last_row = ((max_y - 0.5).floor() as i32).min(self.height - 1);
+
+        let mut crossings = Vec::new();
+        for y in first_row..=last_row {
+            let sample_y = y as f32 + 0.5;
+            crossings.clear();
+            for i in 0..points.len() {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                if (a.1 <= sample_y) != (b.1 <= sample_y) {
+                    crossings.push(a.0 + (sample_y - a.1) * (b.0 - a.0) / (b.1 - a.1));
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            for pair in crossings.chunks_exact(2) {
+                let x0 = (pair[0] - 0.5).ceil() as i32;
+                let x1 = (pair[1] - 0.5).ceil() as i32 - 1;
+                self.span(y, x0, x1);
+            }
+        }
+    }
+
+    fn disc(&mut self, center: (f32, f32), radius: f32) {
+        let first_row = ((center.1 - radius - 0.5).ceil() as i32).max(0);
+        let last_row = ((center.1 + radius - 0.5).floor() as i32).min(self.height - 1);
+        for y in first_row..=last_row {
+            let dy = y as f32 + 0.5 - center.1;
+            let half_width = (radius * radius - dy * dy).max(0.0).sqrt();
+            self.span(
+                y,
+                (center.0 - half_width - 0.5).ceil() as i32,
+                (center.0 + half_width - 0.5).floor() as i32
+            );
+        }
+    }
+}
+
+/// points of the first octant of a bresenham circle, the same as BresenhamCircleDrawer draws
+fn circle_octant(radius: i32, mut point_func: impl FnMut(i32, i32)) {
+    let mut d = 3 - radius * 2;
+    let (mut x, mut y) = (0, radius);
+    point_func(x, y);
+    while x < y {
+        if d <= 0 {
+            d += 6 + (x << 2);
+        } else {
+            d += 10 + ((x - y) << 2);
+            y -= 1;
+        }
+        x += 1;
+        point_func(x, y);
+    }
+}
+
+/// half widths of circle rows, indexed by the distance from the center row
+fn circle_half_widths(radius: i32) -> Vec<i32> {
+    let mut half_widths = vec![0; radius as usize + 1];
+    circle_octant(radius, |x, y| {
+        half_widths[y as usize] = half_widths[y as usize].max(x);
+        half_widths[x as usize] = half_widths[x as usize].max(y);
+    });
+    half_widths
+}
+
+/// points of a quarter of a midpoint ellipse, connected
+fn ellipse_quadrant(rx: i32, ry: i32, mut point_func: impl FnMut(i32, i32)) {
+    if ry == 0 {
+        for x in 0..=rx {
+            point_func(x, 0);
+        }
+        return;
+    }
+    let (rx2, ry2) = (rx as i64 * rx as i64, ry as i64 * ry as i64);
+    let (mut x, mut y) = (0i64, ry as i64);
+    let (mut px, mut py) = (0i64, 2 * rx2 * y);
+
+    let mut p = ry2 - rx2 * ry as i64 + rx2 / 4;
+    while px < py {
+        point_func(x as i32, y as i32);
+        x += 1;
+        px += 2 * ry2;
+        if p < 0 {
+            p += ry2 + px;
+        } else {
+            y -= 1;
+            py -= 2 * rx2;
+            p += ry2 + px - py;
+        }
+    }
+
+    let mut p = (ry2 as f64 * (x as f64 + 0.5).powi(2) + rx2 as f64 * ((y - 1) as f64).powi(2) -
+        (rx2 * ry2) as f64) as i64;
+    while y >= 0 {
+        point_func(x as i32, y as i32);
+        y -= 1;
+        py -= 2 * rx2;
+        if p > 0 {
+            p += rx2 - py;
+        } else {
+            x += 1;
+            px += 2 * ry2;
+            p += rx2 - py + px;
+        }
+    }
+}
+
+pub fn fill_circle(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    center: (i16, i16),
+    radius: i16,
+    fill: ShapeFill
+) {
+    if radius < 0 {
+        return;
+    }
+    let (cx, cy) = (center.0 as i32, center.1 as i32);
+    let mut painter = ShapePainter::new(dest, fill);
+    let half_widths = circle_half_widths(radius as i32);
+    for dy in -(radius as i32)..=radius as i32 {
+        let half_width = half_widths[dy.unsigned_abs() as usize];
+        painter.span(cy + dy, cx - half_width, cx + half_width);
+    }
+}
+
+pub fn draw_ellipse(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    center: (i16, i16),
+    radii: (u16, u16),
+    fill: ShapeFill
+) {
+    let (cx, cy) = (center.0 as i32, center.1 as i32);
+    let mut painter = ShapePainter::new(dest, fill);
+    ellipse_quadrant(radii.0 as i32, radii.1 as i32, |x, y| {
+        painter.plot(cx + x, cy + y);
+        painter.plot(cx - x, cy + y);
+        painter.plot(cx + x, cy - y);
+        painter.plot(cx - x, cy - y);
+    });
+}
+
+pub fn fill_ellipse(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    center: (i16, i16),
+    radii: (u16, u16),
+    fill: ShapeFill
+) {
+    let (cx, cy) = (center.0 as i32, center.1 as i32);
+    let mut half_widths = vec![0; radii.1 as usize + 1];
+    ellipse_quadrant(radii.0 as i32, radii.1 as i32, |x, y| {
+        half_widths[y as usize] = half_widths[y as usize].max(x);
+    });
+    let mut painter = ShapePainter::new(dest, fill);
+    for dy in -(radii.1 as i32)..=radii.1 as i32 {
+        let half_width = half_widths[dy.unsigned_abs() as usize];
+        painter.span(cy + dy, cx - half_width, cx + half_width);
+    }
+}
+
+/// a part of a circle outline going clockwise on screen from start_angle to end_angle.
+/// Angles are in radians, 0 points along +x
+pub fn draw_arc(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    center: (i16, i16),
+    radius: i16,
+    start_angle: f32,
+    end_angle: f32,
+    fill: ShapeFill
+) {
+    use std::f32::consts::TAU;
+    if radius < 0 {
+        return;
+    }
+    let sweep = end_angle - start_angle;
+    if sweep <= 0.0 {
+        return;
+    }
+    let start_angle = start_angle.rem_euclid(TAU);
+    let (cx, cy) = (center.0 as i32, center.1 as i32);
+    let mut painter = ShapePainter::new(dest, fill);
+    let mut plot_in_sweep = |dx: i32, dy: i32| {
+        let angle = (dy as f32).atan2(dx as f32);
+        if sweep >= TAU || (angle - start_angle).rem_euclid(TAU) <= sweep {
+            painter.plot(cx + dx, cy + dy);
+        }
+    };
+    circle_octant(radius as i32, |x, y| {
+        plot_in_sweep(x, y);
+        plot_in_sweep(x, -y);
+        plot_in_sweep(-x, y);
+        plot_in_sweep(-x, -y);
+        plot_in_sweep(y, x);
+        plot_in_sweep(y, -x);
+        plot_in_sweep(-y, x);
+        plot_in_sweep(-y, -x);
+    });
+}
+
+/// corners of a rounded rectangle: (left, top, right, bottom) corner circle centers and a clamped radius
+fn rounded_rectangle_corners(x: i16, y: i16, w: u16, h: u16, radius: u16) -> (i32, i32, i32, i32, i32) {
+    let radius = (radius as i32).min((w as i32 - 1) / 2).min((h as i32 - 1) / 2).max(0);
+    let (x, y) = (x as i32, y as i32);
+    (x + radius, y + radius, x + w as i32 - 1 - radius, y + h as i32 - 1 - radius, radius)
+}
+
+pub fn draw_rounded_rectangle(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    x: i16, y: i16,
+    w: u16, h: u16,
+    radius: u16,
+    fill: ShapeFill
+) {
+    if w == 0 || h == 0 {
+        return;
+    }
+    let (left, top, right, bottom, radius) = rounded_rectangle_corners(x, y, w, h, radius);
+    let mut painter = ShapePainter::new(dest, fill);
+    painter.span(top - radius, left, right);
+    if bottom + radius != top - radius {
+        painter.span(bottom + radius, left, right);
+    }
+    for row in top..=bottom {
+        painter.plot(left - radius, row);
+        if right + radius != left - radius {
+            painter.plot(right + radius, row);
+        }
+    }
+    if radius == 0 {
+        return;
+    }
+    circle_octant(radius, |dx, dy| {
+        for &(px, py) in [(dx, dy), (dy, dx)].iter() {
+            painter.plot(left - px, top - py);
+            painter.plot(right + px, top - py);
+            painter.plot(left - px, bottom + py);
+            painter.plot(right + px, bottom + py);
+        }
+    });
+}
+
+pub fn fill_rounded_rectangle(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    x: i16, y: i16,
+    w: u16, h: u16,
+    radius: u16,
+    fill: ShapeFill
+) {
+    if w == 0 || h == 0 {
+        return;
+    }
+    let (left, top, right, bottom, radius) = rounded_rectangle_corners(x, y, w, h, radius);
+    let half_widths = circle_half_widths(radius);
+    let mut painter = ShapePainter::new(dest, fill);
+    for row in top - radius..=bottom + radius {
+        let dy = if row < top {
+            top - row
+        } else if row > bottom {
+            row - bottom
+        } else {
+            0
+        };
+        let half_width = half_widths[dy as usize];
+        painter.span(row, left - half_width, right + half_width);
+    }
+}
+
+/// width is in pixels, lines 1 pixel wide or thinner are drawn with bresenham
+pub fn draw_thick_line(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    from: (i16, i16),
+    to: (i16, i16),
+    width: f32,
+    cap: ThickLineCap,
+    fill: ShapeFill
+) {
+    let mut painter = ShapePainter::new(dest, fill);
+    if width <= 1.0 {
+        plot_bresenham_line(from.0, from.1, to.0, to.1, |x, y| painter.plot(x as i32, y as i32));
+        return;
+    }
+    let half_width = width / 2.0;
+    let from = (from.0 as f32 + 0.5, from.1 as f32 + 0.5);
+    let to = (to.0 as f32 + 0.5, to.1 as f32 + 0.5);
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+
+    let (dir_x, dir_y) = if length > 0.0 { (dx / length, dy / length) } else { (1.0, 0.0) };
+    let (nx, ny) = (-dir_y * half_width, dir_x * half_width);
+    let extension = match cap {
+        ThickLineCap::Square => half_width,
+        ThickLineCap::Butt | ThickLineCap::Round => 0.0
+    };
+    let (ex, ey) = (dir_x * extension, dir_y * extension);
+
+    if length > 0.0 || !matches!(cap, ThickLineCap::Round) {
+        painter.polygon(&[
+            (from.0 - ex + nx, from.1 - ey + ny),
+            (to.0 + ex + nx, to.1 + ey + ny),
+            (to.0 + ex - nx, to.1 + ey - ny),
+            (from.0 - ex - nx, from.1 - ey - ny)
+        ]);
+    }
+    if let ThickLineCap::Round = cap {
+        painter.disc(from, half_width);
+        if length > 0.0 {
+            painter.disc(to, half_width);
+        }
+    }
+}
+
+/// dashes are alternating lengths of drawn and skipped pixels, starting with a drawn one.
+/// Empty dashes draw a solid line
+pub fn draw_dashed_line(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    from: (i16, i16),
+    to: (i16, i16),
+    dashes: &[u16],
+    fill: ShapeFill
+) {
+    let mut painter = ShapePainter::new(dest, fill);
+    if dashes.iter().all(|&it| it == 0) {
+        plot_bresenham_line(from.0, from.1, to.0, to.1, |x, y| painter.plot(x as i32, y as i32));
+        return;
+    }
+    let mut dash_idx = 0;
+    let mut remaining = dashes[0];
+    plot_bresenham_line(from.0, from.1, to.0, to.1, |x, y| {
+        while remaining == 0 {
+            dash_idx = (dash_idx + 1) % dashes.len();
+            remaining = dashes[dash_idx];
+        }
+        if dash_idx % 2 == 0 {
+            painter.plot(x as i32, y as i32);
+        }
+        remaining -= 1;
+    });
+}
+
+/// scanline fill with the even-odd rule. Pixels are filled when their centers are inside the polygon
+/// going through centers of the given pixels or when they are on its outline,
+/// so the fill covers the same pixels as a closed LineStripRasterizer outline,
+/// e.g. the square (1, 1), (4, 1), (4, 4), (1, 4) fills the same pixels as fill_rectangle(1, 1, 4, 4)
+pub fn fill_polygon(
+    dest: &mut (impl BufferProviderMut<u8> + SizedSurface),
+    points: &[(i16, i16)],
+    fill: ShapeFill
+) {
+    let mut painter = ShapePainter::new(dest, fill);
+    let centers: Vec<(f32, f32)> = points.iter()
+        .map(|p| (p.0 as f32 + 0.5, p.1 as f32 + 0.5))
+        .collect();
+    painter.polygon(&centers);
+    for (i, from) in points.iter().enumerate() {
+        let to = points[(i + 1) % points.len()];
+        plot_bresenham_line(from.0, from.1, to.0, to.1, |x, y| painter.plot(x as i32, y as i32));
+    }
+}
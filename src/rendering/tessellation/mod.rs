@@ -1,10 +1,184 @@
-use lyon::math::{point, Point};
+use lyon::math::{point, vector, Angle, Point};
 use lyon::path::{PathBuffer};
-use lyon::path::builder::PathBuilder;
-use lyon::tessellation::{FillOptions, FillTessellator, VertexBuffers};
+use lyon::path::builder::WithSvg;
+use lyon::tessellation::{FillOptions, FillTessellator, StrokeOptions, StrokeTessellator, TessellationError, VertexBuffers};
 use lyon::tessellation::geometry_builder::simple_builder;
+use thiserror::Error;
 use crate::rendering::deformed_rendering::Vertex;
 
+#[derive(Error, Debug)]
+pub enum PathTessellationError {
+    #[error("Tessellation failed: {0:?}")]
+    TessellationFailed(TessellationError),
+    #[error("Path should start with move_to")]
+    MoveToExpected,
+    #[error("Output doesn't fit in u16 indices")]
+    TooManyVertices
+}
+
+impl From<TessellationError> for PathTessellationError {
+    fn from(e: TessellationError) -> Self {
+        PathTessellationError::TessellationFailed(e)
+    }
+}
+
+/// Decides which parts of overlapping sub-paths are filled, e.g. to make holes
+#[derive(Copy, Clone, Debug)]
+pub enum FillRule {
+    EvenOdd,
+    NonZero
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum LineJoin {
+    Miter,
+    MiterClip,
+    Round,
+    Bevel
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum LineCap {
+    Butt,
+    Square,
+    Round
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn with_width(self, width: f32) -> Self {
+        Self { width, ..self }
+    }
+
+    pub fn with_join(self, join: LineJoin) -> Self {
+        Self { join, ..self }
+    }
+
+    pub fn with_cap(self, cap: LineCap) -> Self {
+        Self { cap, ..self }
+    }
+
+    pub fn with_miter_limit(self, miter_limit: f32) -> Self {
+        Self { miter_limit, ..self }
+    }
+
+    fn to_options(self) -> StrokeOptions {
+        let cap = match self.cap {
+            LineCap::Butt => lyon::tessellation::LineCap::Butt,
+            LineCap::Square => lyon::tessellation::LineCap::Square,
+            LineCap::Round => lyon::tessellation::LineCap::Round
+        };
+        let join = match self.join {
+            LineJoin::Miter => lyon::tessellation::LineJoin::Miter,
+            LineJoin::MiterClip => lyon::tessellation::LineJoin::MiterClip,
+            LineJoin::Round => lyon::tessellation::LineJoin::Round,
+            LineJoin::Bevel => lyon::tessellation::LineJoin::Bevel
+        };
+        StrokeOptions::default()
+            .with_line_width(self.width)
+            .with_line_cap(cap)
+            .with_line_join(join)
+            .with_miter_limit(self.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum PathCommand {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadraticTo { ctrl: Point, to: Point },
+    CubicTo { ctrl1: Point, ctrl2: Point, to: Point },
+    Arc { center: Point, radii: (f32, f32), sweep_angle: f32, x_rotation: f32 },
+    Close
+}
+
+/// A path made of sub-paths with lines, Bézier curves and arcs.
+/// Every move_to starts a new sub-path, which may be used for holes in fills
+#[derive(Clone, Debug, Default)]
+pub struct VectorPath {
+    commands: Vec<PathCommand>
+}
+
+impl VectorPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(mut self, x: f32, y: f32) -> Self {
+        self.commands.push(PathCommand::MoveTo(point(x, y)));
+        self
+    }
+
+    pub fn line_to(mut self, x: f32, y: f32) -> Self {
+        self.commands.push(PathCommand::LineTo(point(x, y)));
+        self
+    }
+
+    pub fn quadratic_bezier_to(mut self, ctrl: (f32, f32), to: (f32, f32)) -> Self {
+        self.commands.push(PathCommand::QuadraticTo {
+            ctrl: point(ctrl.0, ctrl.1),
+            to: point(to.0, to.1)
+        });
+        self
+    }
+
+    pub fn cubic_bezier_to(mut self, ctrl1: (f32, f32), ctrl2: (f32, f32), to: (f32, f32)) -> Self {
+        self.commands.push(PathCommand::CubicTo {
+            ctrl1: point(ctrl1.0, ctrl1.1),
+            ctrl2: point(ctrl2.0, ctrl2.1),
+            to: point(to.0, to.1)
+        });
+        self
+    }
+
+    /// an elliptic arc around the center starting from the current position,
+    /// angles are in radians. If there is no current position it starts a new sub-path
+    /// at the start of the rotated ellipse
+    pub fn arc(mut self, center: (f32, f32), radii: (f32, f32), sweep_angle: f32, x_rotation: f32) -> Self {
+        self.commands.push(PathCommand::Arc {
+            center: point(center.0, center.1),
+            radii,
+            sweep_angle,
+            x_rotation
+        });
+        self
+    }
+
+    /// a closed circular sub-path
+    pub fn circle(self, center: (f32, f32), radius: f32) -> Self {
+        self.move_to(center.0 + radius, center.1)
+            .arc(center, (radius, radius), std::f32::consts::TAU, 0.0)
+            .close()
+    }
+
+    /// the current position goes back to the start of the sub-path,
+    /// so drawing may continue from there without a move_to
+    pub fn close(mut self) -> Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+}
+
+/// Tessellation output is appended to the given buffers. New indices are offset by the amount
+/// of vertices already in the vertex buffer, so several shapes may be collected into one mesh
 pub struct PathTessellator {
     path_buffer: PathBuffer,
     buffers: VertexBuffers<Point, u16>
@@ -23,39 +197,158 @@ impl PathTessellator {
         vertices_to_extend: &mut Vec<Vertex>,
         indices_to_extend: &mut Vec<u16>,
         positions: &[(i16, i16)]
-    ) {
+    ) -> Result<(), PathTessellationError> {
         if positions.len() <= 1 {
-            return;
+            return Ok(());
         }
+        let path = polyline_to_path(positions, true);
+        self.tessellate_path_fill(vertices_to_extend, indices_to_extend, &path, FillRule::EvenOdd)
+    }
 
-        self.path_buffer.clear();
+    pub fn tessellate_polyline_stroke(
+        &mut self,
+        vertices_to_extend: &mut Vec<Vertex>,
+        indices_to_extend: &mut Vec<u16>,
+        positions: &[(i16, i16)],
+        closed: bool,
+        style: &StrokeStyle
+    ) -> Result<(), PathTessellationError> {
+        if positions.len() <= 1 {
+            return Ok(());
+        }
+        let path = polyline_to_path(positions, closed);
+        self.tessellate_path_stroke(vertices_to_extend, indices_to_extend, &path, style)
+    }
 
-        let mut builder = self.path_buffer.builder();
+    pub fn tessellate_path_fill(
+        &mut self,
+        vertices_to_extend: &mut Vec<Vertex>,
+        indices_to_extend: &mut Vec<u16>,
+        path: &VectorPath,
+        fill_rule: FillRule
+    ) -> Result<(), PathTessellationError> {
+        let path_id = self.build_path(path)?;
+        self.buffers.vertices.clear();
+        self.buffers.indices.clear();
 
-        builder.begin(point(positions[0].0 as f32 + 0.5, positions[0].1 as f32 + 0.5));
-        for ix in 0..positions.len() {
-            let pos = positions[(ix + 1) % positions.len()];
-            builder.line_to(point(pos.0 as f32 + 0.5, pos.1 as f32 + 0.5));
+        let fill_rule = match fill_rule {
+            FillRule::EvenOdd => lyon::tessellation::FillRule::EvenOdd,
+            FillRule::NonZero => lyon::tessellation::FillRule::NonZero
+        };
+        let mut tessellator = FillTessellator::new();
+        {
+            let mut geometry_builder = simple_builder(&mut self.buffers);
+            tessellator.tessellate_path(
+                self.path_buffer.get(path_id),
+                &FillOptions::default().with_fill_rule(fill_rule),
+                &mut geometry_builder
+            )?;
         }
-        builder.close();
-        let path_id = builder.build();
 
+        self.extend_output(vertices_to_extend, indices_to_extend)
+    }
+
+    pub fn tessellate_path_stroke(
+        &mut self,
+        vertices_to_extend: &mut Vec<Vertex>,
+        indices_to_extend: &mut Vec<u16>,
+        path: &VectorPath,
+        style: &StrokeStyle
+    ) -> Result<(), PathTessellationError> {
+        let path_id = self.build_path(path)?;
         self.buffers.vertices.clear();
         self.buffers.indices.clear();
 
-        let mut tessellator = FillTessellator::new();
+        let mut tessellator = StrokeTessellator::new();
         {
             let mut geometry_builder = simple_builder(&mut self.buffers);
             tessellator.tessellate_path(
                 self.path_buffer.get(path_id),
-                &FillOptions::default(),
+                &style.to_options(),
                 &mut geometry_builder
-            ).unwrap();
+            )?;
+        }
+
+        self.extend_output(vertices_to_extend, indices_to_extend)
+    }
+
+    fn build_path(&mut self, path: &VectorPath) -> Result<usize, PathTessellationError> {
+        self.path_buffer.clear();
+        let mut builder = WithSvg::new(self.path_buffer.builder());
+        let mut has_position = false;
+        for command in path.commands.iter() {
+            match *command {
+                PathCommand::MoveTo(to) => {
+                    builder.move_to(to);
+                    has_position = true;
+                },
+                PathCommand::LineTo(to) => {
+                    if !has_position {
+                        return Err(PathTessellationError::MoveToExpected);
+                    }
+                    builder.line_to(to);
+                },
+                PathCommand::QuadraticTo { ctrl, to } => {
+                    if !has_position {
+                        return Err(PathTessellationError::MoveToExpected);
+                    }
+                    builder.quadratic_bezier_to(ctrl, to);
+                },
+                PathCommand::CubicTo { ctrl1, ctrl2, to } => {
+                    if !has_position {
+                        return Err(PathTessellationError::MoveToExpected);
+                    }
+                    builder.cubic_bezier_to(ctrl1, ctrl2, to);
+                },
+                PathCommand::Arc { center, radii, sweep_angle, x_rotation } => {
+                    if !has_position {
+                        // the start of the rotated ellipse
+                        let (sin, cos) = x_rotation.sin_cos();
+                        builder.move_to(point(center.x + radii.0 * cos, center.y + radii.0 * sin));
+                        has_position = true;
+                    }
+                    builder.arc(
+                        center,
+                        vector(radii.0, radii.1),
+                        Angle::radians(sweep_angle),
+                        Angle::radians(x_rotation)
+                    );
+                },
+                PathCommand::Close => builder.close()
+            }
         }
+        Ok(builder.build())
+    }
 
+    fn extend_output(
+        &self,
+        vertices_to_extend: &mut Vec<Vertex>,
+        indices_to_extend: &mut Vec<u16>
+    ) -> Result<(), PathTessellationError> {
+        // indices should point into the extended vertex list, which has to stay addressable by u16
+        let vertex_count = vertices_to_extend.len() + self.buffers.vertices.len();
+        if vertex_count > u16::MAX as usize + 1 {
+            return Err(PathTessellationError::TooManyVertices);
+        }
+        let base = vertices_to_extend.len() as u16;
         for vertex in self.buffers.vertices.iter() {
             vertices_to_extend.push(Vertex { position: (vertex.x, vertex.y) })
         }
-        indices_to_extend.extend(self.buffers.indices.iter());
+        for &index in self.buffers.indices.iter() {
+            indices_to_extend.push(index.checked_add(base).ok_or(PathTessellationError::TooManyVertices)?);
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn polyline_to_path(positions: &[(i16, i16)], closed: bool) -> VectorPath {
+    // pixel centers
+    let mut path = VectorPath::new().move_to(positions[0].0 as f32 + 0.5, positions[0].1 as f32 + 0.5);
+    for pos in positions[1..].iter() {
+        path = path.line_to(pos.0 as f32 + 0.5, pos.1 as f32 + 0.5);
+    }
+    if closed {
+        path = path.close();
+    }
+    path
+}
@@ -108,6 +108,18 @@ impl Transform {
         self.actualize_matrix();
     }
 
+    pub fn get_translation(&self) -> (i16, i16) {
+        self.translation
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn get_scale(&self) -> (f32, f32) {
+        self.scale
+    }
+
     pub fn transform_positions(&self, positions: [(f32, f32); 3]) -> [(f32, f32); 3] {
         positions.map(|it| {
             let p = self.matrix * vec3a(it.0, it.1, 1.0);
@@ -0,0 +1,131 @@
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, SizedSurface};
+use crate::rendering::BlittableSurface;
+use crate::rendering::shade_table::ShadeTable;
+
+/// Map coordinates are texels of the height map, y grows downwards
+#[derive(Copy, Clone, Debug)]
+pub struct VoxelCamera {
+    pub position: (f32, f32),
+    /// in the same units as scaled heights of the height map
+    pub height: f32,
+    /// in radians, 0 looks along +x
+    pub yaw: f32,
+    /// in radians, positive values look up. It shifts the horizon, so keep it moderate
+    pub pitch: f32,
+    /// horizontal field of view in radians
+    pub fov: f32,
+    /// how far the terrain is drawn
+    pub view_distance: f32
+}
+
+/// Comanche-style voxel space terrain. The terrain is drawn front to back column by column,
+/// a y-buffer keeps track of the highest drawn pixel of each column, so hidden parts are skipped.
+/// Both maps wrap around
+pub struct VoxelTerrainRenderer<'a> {
+    height_map: &'a BlittableSurface,
+    color_map: &'a BlittableSurface,
+    height_scale: f32,
+    lod_step: f32,
+    fog: Option<(&'a ShadeTable, f32)>
+}
+
+impl<'a> VoxelTerrainRenderer<'a> {
+    pub fn create(height_map: &'a BlittableSurface, color_map: &'a BlittableSurface) -> Self {
+        Self {
+            height_map,
+            color_map,
+            height_scale: 1.0,
+            lod_step: 0.005,
+            fog: None
+        }
+    }
+
+    /// multiplier for height map values. It is 1.0 by default
+    pub fn with_height_scale(self, height_scale: f32) -> Self {
+        Self { height_scale, ..self }
+    }
+
+    /// how fast the sampling distance grows with depth. 0 samples every unit,
+    /// bigger values trade far detail for speed. It is 0.005 by default
+    pub fn with_lod_step(self, lod_step: f32) -> Self {
+        Self { lod_step: lod_step.max(0.0), ..self }
+    }
+
+    /// colors are shaded through the table starting from fog_start,
+    /// reaching the last level at the view distance
+    pub fn with_fog(self, shade_table: &'a ShadeTable, fog_start: f32) -> Self {
+        Self { fog: Some((shade_table, fog_start)), ..self }
+    }
+
+    pub fn draw(&self, destination: &mut (impl BufferProviderMut<u8> + SizedSurface), camera: &VoxelCamera) {
+        let width = destination.get_width();
+        let height = destination.get_height();
+        let (hw, hh) = (self.height_map.get_width() as i32, self.height_map.get_height() as i32);
+        let (cw, ch) = (self.color_map.get_width() as i32, self.color_map.get_height() as i32);
+        if width == 0 || hw == 0 || hh == 0 || cw == 0 || ch == 0 {
+            return;
+        }
+        let buffer = destination.get_buffer_mut();
+        let height_buffer = self.height_map.get_buffer();
+        let color_buffer = self.color_map.get_buffer();
+
+        let half_fov_tan = (camera.fov / 2.0).tan();
+        let focal = width as f32 / 2.0 / half_fov_tan;
+        let horizon = height as f32 / 2.0 + camera.pitch.tan() * focal;
+        let (sin, cos) = camera.yaw.sin_cos();
+        let (forward, right) = ((cos, sin), (-sin, cos));
+
+        let mut y_buffer = vec![height as i32; width];
+
+        let mut z = 1.0f32;
+        let mut dz = 1.0f32;
+        while z < camera.view_distance {
+            // a line across the view at depth z, sampled once per screen column
+            let mut px = camera.position.0 + z * (forward.0 - right.0 * half_fov_tan);
+            let mut py = camera.position.1 + z * (forward.1 - right.1 * half_fov_tan);
+            let step_x = 2.0 * z * half_fov_tan * right.0 / width as f32;
+            let step_y = 2.0 * z * half_fov_tan * right.1 / width as f32;
+            let scale = focal / z;
+            let fog_level = self.fog_level(z, camera.view_distance);
+
+            for x in 0..width {
+                let (ix, iy) = (px.floor() as i32, py.floor() as i32);
+                px += step_x;
+                py += step_y;
+
+                let terrain_height = height_buffer[(iy.rem_euclid(hh) * hw + ix.rem_euclid(hw)) as usize] as f32 *
+                    self.height_scale;
+                let screen_y = ((camera.height - terrain_height) * scale + horizon) as i32;
+                let top = screen_y.max(0);
+                if top >= y_buffer[x] {
+                    continue;
+                }
+
+                let mut color = color_buffer[(iy.rem_euclid(ch) * cw + ix.rem_euclid(cw)) as usize];
+                if let Some((shade_table, _)) = self.fog {
+                    color = shade_table.shade(color, fog_level);
+                }
+                for y in top..y_buffer[x] {
+                    buffer[y as usize * width + x] = color;
+                }
+                y_buffer[x] = top;
+            }
+
+            z += dz;
+            dz += self.lod_step;
+        }
+    }
+
+    fn fog_level(&self, z: f32, view_distance: f32) -> usize {
+        match self.fog {
+            None => 0,
+            Some((shade_table, fog_start)) => {
+                if z <= fog_start || view_distance <= fog_start {
+                    return 0;
+                }
+                let t = (z - fog_start) / (view_distance - fog_start);
+                ((t * shade_table.level_count() as f32) as usize).min(shade_table.level_count() - 1)
+            }
+        }
+    }
+}
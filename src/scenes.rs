@@ -0,0 +1,277 @@
+use crate::transitions::{ScreenSnapshot, Transition, TransitionPlayer};
+#[cfg(feature = "egui")]
+use crate::window::EguiMode;
+use crate::window::{ContextHandler, KeyCode, KeyMods, RetroBlitContext, WindowMode};
+
+/// A screen of the game, e.g. a menu, the gameplay or a pause overlay.
+/// Only the top scene of a SceneStack gets updates and input
+pub trait Scene {
+    /// called once, when the scene gets on the top of the stack for the first time
+    fn init(&mut self, _ctx: &mut RetroBlitContext) {}
+    /// the scene became the top one, either pushed or uncovered by a pop
+    fn on_enter(&mut self, _ctx: &mut RetroBlitContext) {}
+    /// the scene stopped being the top one, either covered by a push or removed
+    fn on_exit(&mut self, _ctx: &mut RetroBlitContext) {}
+    fn update(&mut self, ctx: &mut RetroBlitContext, dt: f32) -> SceneCommand;
+    /// Should redraw the whole screen every frame, the framebuffer isn't cleared between frames
+    /// and transitions leave pixels of the old screen in it. Transparent scenes only have to redraw
+    /// what they cover, as the scenes under them are drawn first
+    fn draw(&mut self, ctx: &mut RetroBlitContext);
+    /// scenes under a transparent one are drawn before it, e.g. the gameplay under a pause menu
+    fn is_transparent(&self) -> bool { false }
+    fn on_mouse_down(&mut self, _ctx: &mut RetroBlitContext, _button_number: u8) {}
+    fn on_mouse_up(&mut self, _ctx: &mut RetroBlitContext, _button_number: u8) {}
+    fn on_key_down(&mut self, _ctx: &mut RetroBlitContext, _key_code: KeyCode, _key_mods: KeyMods) {}
+    fn on_key_up(&mut self, _ctx: &mut RetroBlitContext, _key_code: KeyCode, _key_mods: KeyMods) {}
+    fn on_char(&mut self, _ctx: &mut RetroBlitContext, _character: char) {}
+    fn on_mouse_wheel(&mut self, _ctx: &mut RetroBlitContext, _dx: f32, _dy: f32) {}
+    #[cfg(feature = "egui")]
+    fn egui(&mut self, _ctx: &mut RetroBlitContext, _egui_ctx: egui::Context) {}
+}
+
+enum SceneChange {
+    Stay,
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+    Quit
+}
+
+/// What the stack should do after a scene update
+pub struct SceneCommand {
+    change: SceneChange,
+    transition: Option<Box<dyn Transition>>
+}
+
+impl SceneCommand {
+    fn new(change: SceneChange) -> Self {
+        Self { change, transition: None }
+    }
+
+    pub fn stay() -> Self {
+        Self::new(SceneChange::Stay)
+    }
+
+    /// puts the scene over the current one
+    pub fn push(scene: impl Scene + 'static) -> Self {
+        Self::new(SceneChange::Push(Box::new(scene)))
+    }
+
+    /// removes the current scene, popping the last one quits
+    pub fn pop() -> Self {
+        Self::new(SceneChange::Pop)
+    }
+
+    pub fn replace(scene: impl Scene + 'static) -> Self {
+        Self::new(SceneChange::Replace(Box::new(scene)))
+    }
+
+    pub fn quit() -> Self {
+        Self::new(SceneChange::Quit)
+    }
+
+    /// the transition goes from the last frame of the current scene to the frames after the change
+    pub fn with_transition(self, transition: impl Transition + 'static) -> Self {
+        Self { transition: Some(Box::new(transition)), ..self }
+    }
+}
+
+struct SceneEntry {
+    scene: Box<dyn Scene>,
+    initialized: bool
+}
+
+/// A ContextHandler running a stack of scenes:
+/// ```ignore
+/// retro_blit::window::start(SceneStack::new("My game", WindowMode::Mode13, MainMenu::new()));
+/// ```
+pub struct SceneStack {
+    title: &'static str,
+    window_mode: WindowMode,
+    #[cfg(feature = "egui")]
+    egui_mode: EguiMode,
+    scenes: Vec<SceneEntry>,
+    transition: Option<TransitionPlayer>
+}
+
+impl SceneStack {
+    pub fn new(title: &'static str, window_mode: WindowMode, first_scene: impl Scene + 'static) -> Self {
+        Self {
+            title,
+            window_mode,
+            #[cfg(feature = "egui")]
+            egui_mode: EguiMode::Overlay,
+            scenes: vec![SceneEntry { scene: Box::new(first_scene), initialized: false }],
+            transition: None
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    pub fn with_egui_mode(self, egui_mode: EguiMode) -> Self {
+        Self { egui_mode, ..self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn is_in_transition(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    fn top_scene(&mut self) -> Option<&mut (dyn Scene + 'static)> {
+        self.scenes.last_mut().map(|it| it.scene.as_mut())
+    }
+
+    fn enter_top(&mut self, ctx: &mut RetroBlitContext) {
+        if let Some(entry) = self.scenes.last_mut() {
+            if !entry.initialized {
+                entry.scene.init(ctx);
+                entry.initialized = true;
+            }
+            entry.scene.on_enter(ctx);
+        }
+    }
+
+    fn exit_top(&mut self, ctx: &mut RetroBlitContext) {
+        if let Some(scene) = self.top_scene() {
+            scene.on_exit(ctx);
+        }
+    }
+
+    fn draw_scenes(&mut self, ctx: &mut RetroBlitContext) {
+        let mut first_visible = self.scenes.len().saturating_sub(1);
+        while first_visible > 0 && self.scenes[first_visible].scene.is_transparent() {
+            first_visible -= 1;
+        }
+        for entry in self.scenes[first_visible..].iter_mut() {
+            entry.scene.draw(ctx);
+        }
+    }
+
+    /// from is the frame drawn by the scenes, it's needed when the command has a transition
+    fn apply_command(&mut self, ctx: &mut RetroBlitContext, command: SceneCommand, from: Option<ScreenSnapshot>) {
+        if let SceneChange::Stay = command.change {
+            return;
+        }
+        if let (Some(transition), Some(from)) = (command.transition, from) {
+            // a running transition is dropped, so the palette it shows has to be undone
+            if let Some(player) = self.transition.as_mut() {
+                player.before_draw(ctx);
+            }
+            self.transition = Some(TransitionPlayer::start_from(from, transition));
+        }
+        match command.change {
+            SceneChange::Stay => {},
+            SceneChange::Push(scene) => {
+                self.exit_top(ctx);
+                self.scenes.push(SceneEntry { scene, initialized: false });
+                self.enter_top(ctx);
+            },
+            SceneChange::Pop => {
+                self.exit_top(ctx);
+                self.scenes.pop();
+                if self.scenes.is_empty() {
+                    ctx.quit();
+                } else {
+                    self.enter_top(ctx);
+                }
+            },
+            SceneChange::Replace(scene) => {
+                self.exit_top(ctx);
+                self.scenes.pop();
+                self.scenes.push(SceneEntry { scene, initialized: false });
+                self.enter_top(ctx);
+            },
+            SceneChange::Quit => ctx.quit()
+        }
+    }
+}
+
+impl ContextHandler for SceneStack {
+    fn get_window_title(&self) -> &'static str {
+        self.title
+    }
+
+    fn get_window_mode(&self) -> WindowMode {
+        self.window_mode
+    }
+
+    fn on_mouse_down(&mut self, ctx: &mut RetroBlitContext, button_number: u8) {
+        if let Some(scene) = self.top_scene() {
+            scene.on_mouse_down(ctx, button_number);
+        }
+    }
+
+    fn on_mouse_up(&mut self, ctx: &mut RetroBlitContext, button_number: u8) {
+        if let Some(scene) = self.top_scene() {
+            scene.on_mouse_up(ctx, button_number);
+        }
+    }
+
+    fn on_key_down(&mut self, ctx: &mut RetroBlitContext, key_code: KeyCode, key_mods: KeyMods) {
+        if let Some(scene) = self.top_scene() {
+            scene.on_key_down(ctx, key_code, key_mods);
+        }
+    }
+
+    fn on_key_up(&mut self, ctx: &mut RetroBlitContext, key_code: KeyCode, key_mods: KeyMods) {
+        if let Some(scene) = self.top_scene() {
+            scene.on_key_up(ctx, key_code, key_mods);
+        }
+    }
+
+    fn on_char(&mut self, ctx: &mut RetroBlitContext, character: char) {
+        if let Some(scene) = self.top_scene() {
+            scene.on_char(ctx, character);
+        }
+    }
+
+    fn on_mouse_wheel(&mut self, ctx: &mut RetroBlitContext, dx: f32, dy: f32) {
+        if let Some(scene) = self.top_scene() {
+            scene.on_mouse_wheel(ctx, dx, dy);
+        }
+    }
+
+    fn init(&mut self, ctx: &mut RetroBlitContext) {
+        self.enter_top(ctx);
+    }
+
+    fn update(&mut self, ctx: &mut RetroBlitContext, dt: f32) {
+        if let Some(player) = self.transition.as_mut() {
+            player.before_draw(ctx);
+        }
+        let command = match self.top_scene() {
+            Some(scene) => scene.update(ctx, dt),
+            None => return
+        };
+        self.draw_scenes(ctx);
+        // captured before a running transition mixes the frame with its own old screen
+        let from = match (&command.change, &command.transition) {
+            (SceneChange::Stay, _) | (_, None) => None,
+            _ => Some(ScreenSnapshot::capture(ctx))
+        };
+        if let Some(player) = self.transition.as_mut() {
+            if player.after_draw(ctx, dt) {
+                self.transition = None;
+            }
+        }
+        self.apply_command(ctx, command, from);
+    }
+
+    #[cfg(feature = "egui")]
+    fn get_egui_mode(&self) -> EguiMode {
+        self.egui_mode
+    }
+
+    #[cfg(feature = "egui")]
+    fn egui(&mut self, ctx: &mut RetroBlitContext, egui_ctx: egui::Context) {
+        if let Some(scene) = self.top_scene() {
+            scene.egui(ctx, egui_ctx);
+        }
+    }
+}
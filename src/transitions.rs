@@ -0,0 +1,437 @@
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, SizedSurface};
+use crate::window::RetroBlitContext;
+
+/// The indexed framebuffer and the palette at some moment
+#[derive(Clone)]
+pub struct ScreenSnapshot {
+    width: usize,
+    height: usize,
+    buffer: Vec<u8>,
+    palette: [[u8; 3]; 256]
+}
+
+impl ScreenSnapshot {
+    pub fn capture(ctx: &RetroBlitContext) -> Self {
+        Self {
+            width: ctx.get_width(),
+            height: ctx.get_height(),
+            buffer: ctx.get_buffer().to_vec(),
+            palette: read_palette(ctx)
+        }
+    }
+
+    /// a screen filled with a single color and the current palette, e.g. to fade in from black
+    pub fn solid(ctx: &RetroBlitContext, color_idx: u8) -> Self {
+        Self {
+            width: ctx.get_width(),
+            height: ctx.get_height(),
+            buffer: vec![color_idx; ctx.get_width() * ctx.get_height()],
+            palette: read_palette(ctx)
+        }
+    }
+
+    pub fn get_palette(&self) -> &[[u8; 3]; 256] {
+        &self.palette
+    }
+}
+
+impl SizedSurface for ScreenSnapshot {
+    fn get_width(&self) -> usize { self.width }
+
+    fn get_height(&self) -> usize { self.height }
+}
+
+impl BufferProvider<u8> for ScreenSnapshot {
+    fn get_buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+/// An effect going from the old screen to the new one
+pub trait Transition {
+    /// in seconds
+    fn duration(&self) -> f32;
+
+    /// The new frame is already drawn into the context, the old one is kept in the snapshot.
+    /// Progress goes from 0 to 1. Palette changes are undone before the next frame is drawn
+    fn apply(&mut self, ctx: &mut RetroBlitContext, from: &ScreenSnapshot, progress: f32);
+}
+
+/// Runs a transition over the frames drawn after it started. Scenes of a SceneStack get it for free,
+/// elsewhere it goes around the drawing code:
+/// ```ignore
+/// // when switching
+/// self.transition = Some(TransitionPlayer::start(ctx, transition));
+/// // every update
+/// if let Some(player) = self.transition.as_mut() {
+///     player.before_draw(ctx);
+/// }
+/// self.draw(ctx);
+/// if let Some(player) = self.transition.as_mut() {
+///     if player.after_draw(ctx, dt) {
+///         self.transition = None;
+///     }
+/// }
+/// ```
+pub struct TransitionPlayer {
+    transition: Box<dyn Transition>,
+    from: ScreenSnapshot,
+    /// the palette of the new screen, kept while the transition shows a changed one
+    palette: Option<[[u8; 3]; 256]>,
+    time: f32
+}
+
+impl TransitionPlayer {
+    /// the current content of the context becomes the old screen
+    pub fn start(ctx: &RetroBlitContext, transition: Box<dyn Transition>) -> Self {
+        Self::start_from(ScreenSnapshot::capture(ctx), transition)
+    }
+
+    pub fn start_from(from: ScreenSnapshot, transition: Box<dyn Transition>) -> Self {
+        Self {
+            transition,
+            from,
+            palette: None,
+            time: 0.0
+        }
+    }
+
+    /// undoes palette changes of the previous frame, so the new screen sees its own palette
+    pub fn before_draw(&mut self, ctx: &mut RetroBlitContext) {
+        if let Some(palette) = self.palette.take() {
+            write_palette(ctx, &palette);
+        }
+    }
+
+    /// applies the transition over the drawn frame and advances it.
+    /// Returns true when the transition is over, the palette is left as the new screen set it then
+    pub fn after_draw(&mut self, ctx: &mut RetroBlitContext, dt: f32) -> bool {
+        self.time += dt;
+        let duration = self.transition.duration();
+        if self.time >= duration {
+            return true;
+        }
+        self.palette = Some(read_palette(ctx));
+        self.transition.apply(ctx, &self.from, self.time / duration);
+        false
+    }
+
+    pub fn get_progress(&self) -> f32 {
+        let duration = self.transition.duration();
+        if duration > 0.0 {
+            (self.time / duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+fn read_palette(ctx: &RetroBlitContext) -> [[u8; 3]; 256] {
+    let mut palette = [[0; 3]; 256];
+    for (idx, color) in palette.iter_mut().enumerate() {
+        *color = ctx.get_palette(idx as u8);
+    }
+    palette
+}
+
+fn write_palette(ctx: &mut RetroBlitContext, palette: &[[u8; 3]; 256]) {
+    for (idx, color) in palette.iter().enumerate() {
+        ctx.set_palette(idx as u8, *color);
+    }
+}
+
+/// Fades the old screen out to a color through the palette, then fades the new one in
+pub struct PaletteFade {
+    duration: f32,
+    color: [u8; 3]
+}
+
+impl PaletteFade {
+    pub fn new(duration: f32) -> Self {
+        Self { duration, color: [0, 0, 0] }
+    }
+
+    /// the color in the middle of the fade, black by default
+    pub fn with_color(self, color: [u8; 3]) -> Self {
+        Self { color, ..self }
+    }
+}
+
+impl Transition for PaletteFade {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn apply(&mut self, ctx: &mut RetroBlitContext, from: &ScreenSnapshot, progress: f32) {
+        let palette = if progress < 0.5 {
+            ctx.get_buffer_mut().copy_from_slice(from.get_buffer());
+            *from.get_palette()
+        } else {
+            read_palette(ctx)
+        };
+        let amount = 1.0 - (progress * 2.0 - 1.0).abs();
+        for (idx, color) in palette.iter().enumerate() {
+            let mut faded = [0u8; 3];
+            for channel in 0..3 {
+                let (start, end) = (color[channel] as f32, self.color[channel] as f32);
+                faded[channel] = (start + (end - start) * amount).round() as u8;
+            }
+            ctx.set_palette(idx as u8, faded);
+        }
+    }
+}
+
+/// Columns of the old screen slide down at slightly different times, as in Doom.
+/// Both screens are shown with the palette of the new one
+pub struct Melt {
+    duration: f32,
+    column_width: usize,
+    seed: u32,
+    /// in pixels, per column
+    delays: Vec<f32>
+}
+
+impl Melt {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            column_width: 2,
+            seed: 0x1234_5678,
+            delays: Vec::new()
+        }
+    }
+
+    /// 2 by default
+    pub fn with_column_width(self, column_width: usize) -> Self {
+        Self { column_width: column_width.max(1), ..self }
+    }
+
+    pub fn with_seed(self, seed: u32) -> Self {
+        Self { seed: seed.max(1), ..self }
+    }
+
+    fn generate_delays(&mut self, width: usize, height: usize) {
+        let column_count = width.div_ceil(self.column_width);
+        let max_delay = (height / 12).max(1) as i32;
+        let mut rng = self.seed;
+        let mut delay = (xorshift(&mut rng) % max_delay as u32) as i32;
+        self.delays.clear();
+        for _ in 0..column_count {
+            // neighbour columns differ a little, so the edge looks like dripping
+            delay = (delay + (xorshift(&mut rng) % 3) as i32 - 1).clamp(0, max_delay);
+            self.delays.push(delay as f32);
+        }
+    }
+}
+
+impl Transition for Melt {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn apply(&mut self, ctx: &mut RetroBlitContext, from: &ScreenSnapshot, progress: f32) {
+        let (width, height) = (ctx.get_width(), ctx.get_height());
+        if self.delays.is_empty() {
+            self.generate_delays(width, height);
+        }
+        let max_delay = self.delays.iter().copied().fold(0.0, f32::max);
+        let travel = progress * (height as f32 + max_delay);
+        let buffer = ctx.get_buffer_mut();
+        let old = from.get_buffer();
+        for x in 0..width {
+            let offset = (travel - self.delays[x / self.column_width]).clamp(0.0, height as f32) as usize;
+            for y in offset..height {
+                buffer[y * width + x] = old[(y - offset) * width + x];
+            }
+        }
+    }
+}
+
+/// Pixels switch to the new screen in an ordered dither pattern.
+/// Both screens are shown with the palette of the new one
+pub struct Dissolve {
+    duration: f32
+}
+
+impl Dissolve {
+    pub fn new(duration: f32) -> Self {
+        Self { duration }
+    }
+}
+
+impl Transition for Dissolve {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn apply(&mut self, ctx: &mut RetroBlitContext, from: &ScreenSnapshot, progress: f32) {
+        let width = ctx.get_width();
+        let threshold = (progress * 64.0) as u32;
+        let buffer = ctx.get_buffer_mut();
+        for (idx, (dst, src)) in buffer.iter_mut().zip(from.get_buffer().iter()).enumerate() {
+            if bayer_8x8(idx % width, idx / width) >= threshold {
+                *dst = *src;
+            }
+        }
+    }
+}
+
+/// A circle opening from the center point shows the new screen.
+/// Both screens are shown with the palette of the new one
+pub struct Iris {
+    duration: f32,
+    center: Option<(i16, i16)>
+}
+
+impl Iris {
+    pub fn new(duration: f32) -> Self {
+        Self { duration, center: None }
+    }
+
+    /// the screen center by default
+    pub fn with_center(self, center: (i16, i16)) -> Self {
+        Self { center: Some(center), ..self }
+    }
+}
+
+impl Transition for Iris {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn apply(&mut self, ctx: &mut RetroBlitContext, from: &ScreenSnapshot, progress: f32) {
+        let (width, height) = (ctx.get_width(), ctx.get_height());
+        let (cx, cy) = match self.center {
+            Some((x, y)) => (x as f32, y as f32),
+            None => (width as f32 / 2.0, height as f32 / 2.0)
+        };
+        // the circle has to cover the farthest corner at the end
+        let corners = [(0.0, 0.0), (width as f32, 0.0), (0.0, height as f32), (width as f32, height as f32)];
+        let max_radius_sq = corners.iter()
+            .map(|&(x, y)| (x - cx) * (x - cx) + (y - cy) * (y - cy))
+            .fold(0.0, f32::max);
+        let radius_sq = max_radius_sq * progress * progress;
+        let buffer = ctx.get_buffer_mut();
+        let old = from.get_buffer();
+        for y in 0..height {
+            let dy = y as f32 + 0.5 - cy;
+            for x in 0..width {
+                let dx = x as f32 + 0.5 - cx;
+                if dx * dx + dy * dy > radius_sq {
+                    buffer[y * width + x] = old[y * width + x];
+                }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WipeDirection {
+    LeftToRight,
+    RightToLeft,
+    TopToBottom,
+    BottomToTop
+}
+
+/// A straight edge moving over the screen shows the new screen behind it.
+/// Both screens are shown with the palette of the new one
+pub struct Wipe {
+    duration: f32,
+    direction: WipeDirection
+}
+
+impl Wipe {
+    pub fn new(duration: f32, direction: WipeDirection) -> Self {
+        Self { duration, direction }
+    }
+}
+
+impl Transition for Wipe {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn apply(&mut self, ctx: &mut RetroBlitContext, from: &ScreenSnapshot, progress: f32) {
+        let (width, height) = (ctx.get_width(), ctx.get_height());
+        let edge_x = (progress * width as f32) as usize;
+        let edge_y = (progress * height as f32) as usize;
+        // the range of the old screen still visible
+        let (columns, rows) = match self.direction {
+            WipeDirection::LeftToRight => (edge_x..width, 0..height),
+            WipeDirection::RightToLeft => (0..width - edge_x, 0..height),
+            WipeDirection::TopToBottom => (0..width, edge_y..height),
+            WipeDirection::BottomToTop => (0..width, 0..height - edge_y)
+        };
+        let buffer = ctx.get_buffer_mut();
+        let old = from.get_buffer();
+        for y in rows {
+            let row = y * width;
+            buffer[row + columns.start..row + columns.end].copy_from_slice(&old[row + columns.start..row + columns.end]);
+        }
+    }
+}
+
+/// The old screen breaks into growing blocks, then the new one gets sharp from the same blocks
+pub struct Pixelate {
+    duration: f32,
+    max_block_size: usize
+}
+
+impl Pixelate {
+    pub fn new(duration: f32) -> Self {
+        Self { duration, max_block_size: 16 }
+    }
+
+    /// block size in the middle of the transition, 16 by default
+    pub fn with_max_block_size(self, max_block_size: usize) -> Self {
+        Self { max_block_size: max_block_size.max(1), ..self }
+    }
+}
+
+impl Transition for Pixelate {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn apply(&mut self, ctx: &mut RetroBlitContext, from: &ScreenSnapshot, progress: f32) {
+        let width = ctx.get_width();
+        if progress < 0.5 {
+            ctx.get_buffer_mut().copy_from_slice(from.get_buffer());
+            write_palette(ctx, from.get_palette());
+        }
+        let amount = 1.0 - (progress * 2.0 - 1.0).abs();
+        let block_size = 1 + ((self.max_block_size - 1) as f32 * amount).round() as usize;
+        if block_size == 1 || width == 0 {
+            return;
+        }
+        let buffer = ctx.get_buffer_mut();
+        let height = buffer.len() / width;
+        for block_y in (0..height).step_by(block_size) {
+            for block_x in (0..width).step_by(block_size) {
+                let color = buffer[block_y * width + block_x];
+                for y in block_y..(block_y + block_size).min(height) {
+                    let row = y * width;
+                    for px in &mut buffer[row + block_x..row + (block_x + block_size).min(width)] {
+                        *px = color;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn xorshift(state: &mut u32) -> u32 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *state = x;
+    x
+}
+
+/// 0..64, ordered so every threshold step spreads evenly over the 8x8 tile
+fn bayer_8x8(x: usize, y: usize) -> u32 {
+    let (x, y) = (x as u32 & 7, y as u32 & 7);
+    let xy = x ^ y;
+    ((xy & 1) << 5) | ((x & 1) << 4) | ((xy & 2) << 2) | ((x & 2) << 1) | ((xy & 4) >> 1) | ((x & 4) >> 2)
+}
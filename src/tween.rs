@@ -0,0 +1,506 @@
+use std::f32::consts::PI;
+use glam::{Vec2, Vec3};
+use crate::rendering::transform::Transform;
+
+/// Standard easing curves, see easings.net for the pictures
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    /// overshoots a little
+    BackIn,
+    BackOut,
+    BackInOut,
+    /// overshoots and wobbles
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+    /// jumps through the given count of equal steps, handy for choppy retro motion
+    Steps(u8)
+}
+
+#[derive(Copy, Clone)]
+enum Curve {
+    Quad,
+    Cubic,
+    Quart,
+    Sine,
+    Expo,
+    Circ,
+    Back,
+    Elastic,
+    Bounce
+}
+
+#[derive(Copy, Clone)]
+enum EaseMode {
+    In,
+    Out,
+    InOut
+}
+
+impl Easing {
+    /// maps t from 0..1 to the eased value, which is 0 at the start and 1 at the end
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        let (curve, mode) = match self {
+            Easing::Linear => return t,
+            Easing::Steps(count) => {
+                let count = count.max(1) as f32;
+                return (t * count).floor().min(count) / count;
+            },
+            Easing::QuadIn => (Curve::Quad, EaseMode::In),
+            Easing::QuadOut => (Curve::Quad, EaseMode::Out),
+            Easing::QuadInOut => (Curve::Quad, EaseMode::InOut),
+            Easing::CubicIn => (Curve::Cubic, EaseMode::In),
+            Easing::CubicOut => (Curve::Cubic, EaseMode::Out),
+            Easing::CubicInOut => (Curve::Cubic, EaseMode::InOut),
+            Easing::QuartIn => (Curve::Quart, EaseMode::In),
+            Easing::QuartOut => (Curve::Quart, EaseMode::Out),
+            Easing::QuartInOut => (Curve::Quart, EaseMode::InOut),
+            Easing::SineIn => (Curve::Sine, EaseMode::In),
+            Easing::SineOut => (Curve::Sine, EaseMode::Out),
+            Easing::SineInOut => (Curve::Sine, EaseMode::InOut),
+            Easing::ExpoIn => (Curve::Expo, EaseMode::In),
+            Easing::ExpoOut => (Curve::Expo, EaseMode::Out),
+            Easing::ExpoInOut => (Curve::Expo, EaseMode::InOut),
+            Easing::CircIn => (Curve::Circ, EaseMode::In),
+            Easing::CircOut => (Curve::Circ, EaseMode::Out),
+            Easing::CircInOut => (Curve::Circ, EaseMode::InOut),
+            Easing::BackIn => (Curve::Back, EaseMode::In),
+            Easing::BackOut => (Curve::Back, EaseMode::Out),
+            Easing::BackInOut => (Curve::Back, EaseMode::InOut),
+            Easing::ElasticIn => (Curve::Elastic, EaseMode::In),
+            Easing::ElasticOut => (Curve::Elastic, EaseMode::Out),
+            Easing::ElasticInOut => (Curve::Elastic, EaseMode::InOut),
+            Easing::BounceIn => (Curve::Bounce, EaseMode::In),
+            Easing::BounceOut => (Curve::Bounce, EaseMode::Out),
+            Easing::BounceInOut => (Curve::Bounce, EaseMode::InOut)
+        };
+        // every curve is defined by its "in" form, the other forms mirror it
+        match mode {
+            EaseMode::In => curve.ease_in(t),
+            EaseMode::Out => 1.0 - curve.ease_in(1.0 - t),
+            EaseMode::InOut if t < 0.5 => curve.ease_in(t * 2.0) / 2.0,
+            EaseMode::InOut => 1.0 - curve.ease_in(2.0 - t * 2.0) / 2.0
+        }
+    }
+}
+
+impl Curve {
+    fn ease_in(self, t: f32) -> f32 {
+        match self {
+            Curve::Quad => t * t,
+            Curve::Cubic => t * t * t,
+            Curve::Quart => t * t * t * t,
+            Curve::Sine => 1.0 - (t * PI / 2.0).cos(),
+            Curve::Expo => if t <= 0.0 { 0.0 } else { 2f32.powf(10.0 * t - 10.0) },
+            Curve::Circ => 1.0 - (1.0 - t * t).max(0.0).sqrt(),
+            Curve::Back => {
+                const OVERSHOOT: f32 = 1.70158;
+                (OVERSHOOT + 1.0) * t * t * t - OVERSHOOT * t * t
+            },
+            Curve::Elastic => {
+                if t <= 0.0 || t >= 1.0 {
+                    return t;
+                }
+                -(2f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin()
+            },
+            Curve::Bounce => 1.0 - bounce_out(1.0 - t)
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// A value which can be interpolated. t is eased, so it may go a little outside of 0..1
+pub trait Tweenable: Clone {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self;
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+impl Tweenable for f32 {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        lerp(*from, *to, t)
+    }
+}
+
+impl Tweenable for f64 {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        from + (to - from) * t as f64
+    }
+}
+
+impl Tweenable for i16 {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        lerp(*from as f32, *to as f32, t).round() as i16
+    }
+}
+
+impl Tweenable for i32 {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        lerp(*from as f32, *to as f32, t).round() as i32
+    }
+}
+
+impl Tweenable for u8 {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        lerp(*from as f32, *to as f32, t).round().clamp(0.0, 255.0) as u8
+    }
+}
+
+impl Tweenable for (f32, f32) {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        (lerp(from.0, to.0, t), lerp(from.1, to.1, t))
+    }
+}
+
+/// screen positions
+impl Tweenable for (i16, i16) {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        (i16::tween(&from.0, &to.0, t), i16::tween(&from.1, &to.1, t))
+    }
+}
+
+/// palette colors as returned by RetroBlitContext::get_palette
+impl Tweenable for [u8; 3] {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        [
+            u8::tween(&from[0], &to[0], t),
+            u8::tween(&from[1], &to[1], t),
+            u8::tween(&from[2], &to[2], t)
+        ]
+    }
+}
+
+impl Tweenable for Vec2 {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        from.lerp(*to, t)
+    }
+}
+
+impl Tweenable for Vec3 {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        from.lerp(*to, t)
+    }
+}
+
+/// translation, rotation and scale are tweened separately, rotation goes the plain way
+/// without wrapping around
+impl Tweenable for Transform {
+    fn tween(from: &Self, to: &Self, t: f32) -> Self {
+        Transform::from_angle_translation_scale(
+            lerp(from.get_rotation(), to.get_rotation(), t),
+            Tweenable::tween(&from.get_translation(), &to.get_translation(), t),
+            Tweenable::tween(&from.get_scale(), &to.get_scale(), t)
+        )
+    }
+}
+
+/// Anything giving a value at a point of time. Animations are stateless,
+/// play them with a TweenPlayer or sample them with your own clock
+pub trait Animation {
+    type Value;
+
+    /// in seconds, infinite for endless repeats
+    fn duration(&self) -> f32;
+
+    /// time is clamped to the duration
+    fn sample(&self, time: f32) -> Self::Value;
+
+    fn repeat(self, repeat: Repeat) -> Repeated<Self> where Self: Sized {
+        Repeated { animation: self, repeat, yoyo: false }
+    }
+
+    /// every other repetition is played backwards, so Repeat::Times(2) goes there and back
+    fn yoyo(self, repeat: Repeat) -> Repeated<Self> where Self: Sized {
+        Repeated { animation: self, repeat, yoyo: true }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Tween<T: Tweenable> {
+    from: T,
+    to: T,
+    duration: f32,
+    delay: f32,
+    easing: Easing
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration: duration.max(0.0),
+            delay: 0.0,
+            easing: Easing::Linear
+        }
+    }
+
+    /// keeps the value for the duration, a pause within sequences
+    pub fn hold(value: T, duration: f32) -> Self {
+        Self::new(value.clone(), value, duration)
+    }
+
+    pub fn with_easing(self, easing: Easing) -> Self {
+        Self { easing, ..self }
+    }
+
+    /// the value stays at from during the delay
+    pub fn with_delay(self, delay: f32) -> Self {
+        Self { delay: delay.max(0.0), ..self }
+    }
+}
+
+impl<T: Tweenable> Animation for Tween<T> {
+    type Value = T;
+
+    fn duration(&self) -> f32 {
+        self.delay + self.duration
+    }
+
+    fn sample(&self, time: f32) -> T {
+        let t = if self.duration > 0.0 {
+            (time - self.delay) / self.duration
+        } else if time >= self.delay {
+            1.0
+        } else {
+            0.0
+        };
+        T::tween(&self.from, &self.to, self.easing.apply(t))
+    }
+}
+
+/// Animations of the same value played one after another
+pub struct Sequence<T> {
+    steps: Vec<Box<dyn Animation<Value = T>>>,
+    duration: f32
+}
+
+impl<T> Sequence<T> {
+    pub fn new(first: impl Animation<Value = T> + 'static) -> Self {
+        Self {
+            duration: first.duration(),
+            steps: vec![Box::new(first)]
+        }
+    }
+
+    pub fn then(mut self, step: impl Animation<Value = T> + 'static) -> Self {
+        self.duration += step.duration();
+        self.steps.push(Box::new(step));
+        self
+    }
+}
+
+impl<T> Animation for Sequence<T> {
+    type Value = T;
+
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn sample(&self, time: f32) -> T {
+        let mut time = time.max(0.0);
+        let last = self.steps.len() - 1;
+        for (idx, step) in self.steps.iter().enumerate() {
+            let duration = step.duration();
+            if time < duration || idx == last {
+                return step.sample(time);
+            }
+            time -= duration;
+        }
+        unreachable!()
+    }
+}
+
+/// Two animations played at once, nest them for more.
+/// The value is the pair of their values
+pub struct Parallel<A: Animation, B: Animation> {
+    first: A,
+    second: B
+}
+
+impl<A: Animation, B: Animation> Parallel<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: Animation, B: Animation> Animation for Parallel<A, B> {
+    type Value = (A::Value, B::Value);
+
+    fn duration(&self) -> f32 {
+        self.first.duration().max(self.second.duration())
+    }
+
+    fn sample(&self, time: f32) -> Self::Value {
+        (self.first.sample(time), self.second.sample(time))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    Times(u32),
+    Forever
+}
+
+pub struct Repeated<A: Animation> {
+    animation: A,
+    repeat: Repeat,
+    yoyo: bool
+}
+
+impl<A: Animation> Animation for Repeated<A> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        match self.repeat {
+            Repeat::Times(count) => self.animation.duration() * count as f32,
+            Repeat::Forever => f32::INFINITY
+        }
+    }
+
+    fn sample(&self, time: f32) -> A::Value {
+        let cycle = self.animation.duration();
+        if cycle <= 0.0 {
+            return self.animation.sample(0.0);
+        }
+        let time = time.max(0.0);
+        let mut cycle_idx = (time / cycle).floor();
+        if let Repeat::Times(count) = self.repeat {
+            cycle_idx = cycle_idx.min(count.max(1) as f32 - 1.0);
+        }
+        let local_time = (time - cycle_idx * cycle).min(cycle);
+        if self.yoyo && cycle_idx as u32 % 2 == 1 {
+            self.animation.sample(cycle - local_time)
+        } else {
+            self.animation.sample(local_time)
+        }
+    }
+}
+
+/// Plays an animation in real time:
+/// ```ignore
+/// let mut hop = TweenPlayer::new(
+///     Sequence::new(Tween::new((0, 0), (0, -8), 0.15).with_easing(Easing::QuadOut))
+///         .then(Tween::new((0, -8), (0, 0), 0.15).with_easing(Easing::BounceOut))
+/// );
+/// ...
+/// hop.update(dt);
+/// let (dx, dy) = hop.value();
+/// ```
+pub struct TweenPlayer<A: Animation> {
+    animation: A,
+    time: f32,
+    paused: bool,
+    finished: bool,
+    on_complete: Option<Box<dyn FnMut()>>
+}
+
+impl<A: Animation> TweenPlayer<A> {
+    pub fn new(animation: A) -> Self {
+        Self {
+            animation,
+            time: 0.0,
+            paused: false,
+            finished: false,
+            on_complete: None
+        }
+    }
+
+    /// called once by the update which finishes the animation
+    pub fn with_on_complete(self, on_complete: impl FnMut() + 'static) -> Self {
+        Self { on_complete: Some(Box::new(on_complete)), ..self }
+    }
+
+    /// returns true only on the update which finishes the animation
+    pub fn update(&mut self, dt: f32) -> bool {
+        if self.paused || self.finished {
+            return false;
+        }
+        self.time += dt;
+        if self.time < self.animation.duration() {
+            return false;
+        }
+        self.time = self.animation.duration();
+        self.finished = true;
+        if let Some(on_complete) = self.on_complete.as_mut() {
+            on_complete();
+        }
+        true
+    }
+
+    pub fn value(&self) -> A::Value {
+        self.animation.sample(self.time)
+    }
+
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+
+    /// jumps to the time without calling the completion callback
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(0.0, self.animation.duration());
+        self.finished = self.time >= self.animation.duration();
+    }
+
+    pub fn reset(&mut self) {
+        self.seek(0.0);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn get_animation(&self) -> &A {
+        &self.animation
+    }
+}
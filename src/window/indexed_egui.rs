@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::time::Instant;
+use egui::epaint::{ClippedPrimitive, Color32, ImageData, ImageDelta, Mesh, Primitive};
+use egui::{Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, TextureId, Vec2};
+use crate::window::{KeyCode, KeyMods, RetroBlitContext};
+
+/// wheel steps are turned into this many buffer pixels of scrolling
+const SCROLL_STEP: f32 = 8.0;
+
+/// 5 bits per channel
+const NEAREST_COLORS_SIZE: usize = 32 * 32 * 32;
+const UNKNOWN_COLOR: u16 = u16::MAX;
+
+struct IndexedTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color32>
+}
+
+/// Runs egui in the framebuffer pixel space and rasterizes its meshes right into the indexed buffer.
+/// Colors are quantized to the nearest palette entries and pixels less than half opaque are skipped,
+/// so text and widgets get the crisp look of the rest of the picture
+pub(crate) struct IndexedEgui {
+    egui_ctx: egui::Context,
+    textures: HashMap<TextureId, IndexedTexture>,
+    palette: [u8; 256 * 3],
+    nearest_colors: Vec<u16>,
+    /// key and text events since the previous frame, in the order they came
+    key_events: Vec<Event>,
+    start_instant: Instant
+}
+
+impl IndexedEgui {
+    pub(crate) fn new() -> Self {
+        let egui_ctx = egui::Context::default();
+        // anti aliasing fringes would only turn into noise after the quantization
+        egui_ctx.tessellation_options().feathering = false;
+        Self {
+            egui_ctx,
+            textures: HashMap::new(),
+            palette: [0; 256 * 3],
+            nearest_colors: vec![UNKNOWN_COLOR; NEAREST_COLORS_SIZE],
+            key_events: Vec::new(),
+            start_instant: Instant::now()
+        }
+    }
+
+    pub(crate) fn egui_ctx(&self) -> &egui::Context {
+        &self.egui_ctx
+    }
+
+    /// key repeats come as presses too, so held keys like backspace or arrows keep working in text fields
+    pub(crate) fn key_event(&mut self, key_code: KeyCode, key_mods: KeyMods, pressed: bool) {
+        if let Some(key) = to_egui_key(key_code) {
+            self.key_events.push(Event::Key { key, pressed, modifiers: to_egui_modifiers(key_mods) });
+        }
+    }
+
+    pub(crate) fn char_event(&mut self, character: char) {
+        self.key_events.push(Event::Text(character.to_string()));
+    }
+
+    pub(crate) fn begin_frame(&mut self, ctx: &RetroBlitContext) {
+        let modifiers = to_egui_modifiers(ctx.key_mods_pressed);
+
+        let mut events = Vec::new();
+        let pos = Pos2::new(ctx.mouse_x, ctx.mouse_y);
+        if ctx.pointer_inside {
+            events.push(Event::PointerMoved(pos));
+        } else {
+            events.push(Event::PointerGone);
+        }
+        let buttons = [PointerButton::Primary, PointerButton::Middle, PointerButton::Secondary];
+        for (idx, &button) in buttons.iter().enumerate() {
+            if ctx.mouse_buttons_just_pressed[idx] {
+                events.push(Event::PointerButton { pos, button, pressed: true, modifiers });
+            }
+            if ctx.mouse_buttons_just_released[idx] {
+                events.push(Event::PointerButton { pos, button, pressed: false, modifiers });
+            }
+        }
+        let (wheel_x, wheel_y) = ctx.mouse_wheel_delta;
+        if wheel_x != 0.0 || wheel_y != 0.0 {
+            events.push(Event::Scroll(Vec2::new(wheel_x, wheel_y) * SCROLL_STEP));
+        }
+        events.append(&mut self.key_events);
+
+        self.egui_ctx.begin_frame(RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                Vec2::new(ctx.buffer_width as f32, ctx.buffer_height as f32)
+            )),
+            pixels_per_point: Some(1.0),
+            time: Some(self.start_instant.elapsed().as_secs_f64()),
+            modifiers,
+            events,
+            ..Default::default()
+        });
+    }
+
+    pub(crate) fn end_frame(&mut self, ctx: &mut RetroBlitContext) {
+        let output = self.egui_ctx.end_frame();
+        for (texture_id, delta) in output.textures_delta.set {
+            self.set_texture(texture_id, delta);
+        }
+
+        if self.palette[..] != ctx.colors[..] {
+            self.palette = ctx.colors;
+            for nearest in self.nearest_colors.iter_mut() {
+                *nearest = UNKNOWN_COLOR;
+            }
+        }
+
+        for ClippedPrimitive { clip_rect, primitive } in self.egui_ctx.tessellate(output.shapes) {
+            if let Primitive::Mesh(mesh) = primitive {
+                self.rasterize_mesh(ctx, &mesh, clip_rect);
+            }
+        }
+
+        for texture_id in output.textures_delta.free {
+            self.textures.remove(&texture_id);
+        }
+    }
+
+    fn set_texture(&mut self, texture_id: TextureId, delta: ImageDelta) {
+        let (width, height) = (delta.image.width(), delta.image.height());
+        let pixels: Vec<Color32> = match delta.image {
+            ImageData::Color(image) => image.pixels,
+            ImageData::Font(image) => image.srgba_pixels(1.0).collect()
+        };
+        match delta.pos {
+            None => {
+                self.textures.insert(texture_id, IndexedTexture { width, height, pixels });
+            },
+            Some([x, y]) => {
+                let texture = match self.textures.get_mut(&texture_id) {
+                    Some(texture) => texture,
+                    None => return
+                };
+                for (row, patch_row) in pixels.chunks_exact(width.max(1)).enumerate() {
+                    if y + row >= texture.height || x >= texture.width {
+                        break;
+                    }
+                    let stride = (y + row) * texture.width + x;
+                    let len = patch_row.len().min(texture.width - x);
+                    texture.pixels[stride..stride + len].copy_from_slice(&patch_row[..len]);
+                }
+            }
+        }
+    }
+
+    fn rasterize_mesh(&mut self, ctx: &mut RetroBlitContext, mesh: &Mesh, clip_rect: Rect) {
+        let texture = match self.textures.get(&mesh.texture_id) {
+            Some(texture) => texture,
+            None => return
+        };
+        let (buffer_width, buffer_height) = (ctx.buffer_width as i32, ctx.buffer_height as i32);
+        let clip_left = (clip_rect.min.x.floor() as i32).max(0);
+        let clip_top = (clip_rect.min.y.floor() as i32).max(0);
+        let clip_right = (clip_rect.max.x.ceil() as i32).min(buffer_width);
+        let clip_bottom = (clip_rect.max.y.ceil() as i32).min(buffer_height);
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let v0 = &mesh.vertices[triangle[0] as usize];
+            let v1 = &mesh.vertices[triangle[1] as usize];
+            let v2 = &mesh.vertices[triangle[2] as usize];
+            let area = edge(v0.pos, v1.pos, v2.pos);
+            if area == 0.0 {
+                continue;
+            }
+            let left = (v0.pos.x.min(v1.pos.x).min(v2.pos.x).floor() as i32).max(clip_left);
+            let top = (v0.pos.y.min(v1.pos.y).min(v2.pos.y).floor() as i32).max(clip_top);
+            let right = (v0.pos.x.max(v1.pos.x).max(v2.pos.x).ceil() as i32).min(clip_right);
+            let bottom = (v0.pos.y.max(v1.pos.y).max(v2.pos.y).ceil() as i32).min(clip_bottom);
+
+            for y in top..bottom {
+                for x in left..right {
+                    let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    // dividing by the signed area makes both windings work
+                    let w0 = edge(v1.pos, v2.pos, p) / area;
+                    let w1 = edge(v2.pos, v0.pos, p) / area;
+                    let w2 = edge(v0.pos, v1.pos, p) / area;
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+                    let u = w0 * v0.uv.x + w1 * v1.uv.x + w2 * v2.uv.x;
+                    let v = w0 * v0.uv.y + w1 * v1.uv.y + w2 * v2.uv.y;
+                    let tx = ((u * texture.width as f32) as usize).min(texture.width.max(1) - 1);
+                    let ty = ((v * texture.height as f32) as usize).min(texture.height.max(1) - 1);
+                    let texel = texture.pixels.get(ty * texture.width + tx).copied().unwrap_or(Color32::WHITE);
+
+                    // colors are premultiplied, so modulation is a plain product
+                    let mut rgba = [0f32; 4];
+                    for (channel, value) in rgba.iter_mut().enumerate() {
+                        let vertex_value = w0 * v0.color[channel] as f32 +
+                            w1 * v1.color[channel] as f32 +
+                            w2 * v2.color[channel] as f32;
+                        *value = vertex_value * texel[channel] as f32 / 255.0;
+                    }
+                    let alpha = rgba[3];
+                    if alpha < 128.0 {
+                        continue;
+                    }
+                    let unpremultiply = 255.0 / alpha;
+                    let color = [
+                        (rgba[0] * unpremultiply).min(255.0) as u8,
+                        (rgba[1] * unpremultiply).min(255.0) as u8,
+                        (rgba[2] * unpremultiply).min(255.0) as u8
+                    ];
+                    let idx = nearest_color(&self.palette, &mut self.nearest_colors, color);
+                    ctx.buffer_pixels[(y * buffer_width + x) as usize] = idx;
+                }
+            }
+        }
+    }
+}
+
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn nearest_color(palette: &[u8; 256 * 3], nearest_colors: &mut [u16], color: [u8; 3]) -> u8 {
+    let key = ((color[0] as usize >> 3) << 10) | ((color[1] as usize >> 3) << 5) | (color[2] as usize >> 3);
+    if nearest_colors[key] == UNKNOWN_COLOR {
+        let mut best = (0, i32::MAX);
+        for (idx, entry) in palette.chunks_exact(3).enumerate() {
+            let dr = entry[0] as i32 - color[0] as i32;
+            let dg = entry[1] as i32 - color[1] as i32;
+            let db = entry[2] as i32 - color[2] as i32;
+            let distance = dr * dr + dg * dg + db * db;
+            if distance < best.1 {
+                best = (idx, distance);
+            }
+        }
+        nearest_colors[key] = best.0 as u16;
+    }
+    nearest_colors[key] as u8
+}
+
+fn to_egui_modifiers(key_mods: KeyMods) -> Modifiers {
+    Modifiers {
+        alt: key_mods.option,
+        ctrl: key_mods.control,
+        shift: key_mods.shift,
+        mac_cmd: cfg!(target_os = "macos") && key_mods.command,
+        command: if cfg!(target_os = "macos") {
+            key_mods.command
+        } else {
+            key_mods.control
+        }
+    }
+}
+
+fn to_egui_key(key_code: KeyCode) -> Option<Key> {
+    let key = match key_code {
+        KeyCode::Down => Key::ArrowDown,
+        KeyCode::Left => Key::ArrowLeft,
+        KeyCode::Right => Key::ArrowRight,
+        KeyCode::Up => Key::ArrowUp,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Enter | KeyCode::KpEnter => Key::Enter,
+        KeyCode::Space => Key::Space,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Key0 | KeyCode::Kp0 => Key::Num0,
+        KeyCode::Key1 | KeyCode::Kp1 => Key::Num1,
+        KeyCode::Key2 | KeyCode::Kp2 => Key::Num2,
+        KeyCode::Key3 | KeyCode::Kp3 => Key::Num3,
+        KeyCode::Key4 | KeyCode::Kp4 => Key::Num4,
+        KeyCode::Key5 | KeyCode::Kp5 => Key::Num5,
+        KeyCode::Key6 | KeyCode::Kp6 => Key::Num6,
+        KeyCode::Key7 | KeyCode::Kp7 => Key::Num7,
+        KeyCode::Key8 | KeyCode::Kp8 => Key::Num8,
+        KeyCode::Key9 | KeyCode::Kp9 => Key::Num9,
+        KeyCode::A => Key::A,
+        KeyCode::B => Key::B,
+        KeyCode::C => Key::C,
+        KeyCode::D => Key::D,
+        KeyCode::E => Key::E,
+        KeyCode::F => Key::F,
+        KeyCode::G => Key::G,
+        KeyCode::H => Key::H,
+        KeyCode::I => Key::I,
+        KeyCode::J => Key::J,
+        KeyCode::K => Key::K,
+        KeyCode::L => Key::L,
+        KeyCode::M => Key::M,
+        KeyCode::N => Key::N,
+        KeyCode::O => Key::O,
+        KeyCode::P => Key::P,
+        KeyCode::Q => Key::Q,
+        KeyCode::R => Key::R,
+        KeyCode::S => Key::S,
+        KeyCode::T => Key::T,
+        KeyCode::U => Key::U,
+        KeyCode::V => Key::V,
+        KeyCode::W => Key::W,
+        KeyCode::X => Key::X,
+        KeyCode::Y => Key::Y,
+        KeyCode::Z => Key::Z,
+        KeyCode::F1 => Key::F1,
+        KeyCode::F2 => Key::F2,
+        KeyCode::F3 => Key::F3,
+        KeyCode::F4 => Key::F4,
+        KeyCode::F5 => Key::F5,
+        KeyCode::F6 => Key::F6,
+        KeyCode::F7 => Key::F7,
+        KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9,
+        KeyCode::F10 => Key::F10,
+        KeyCode::F11 => Key::F11,
+        KeyCode::F12 => Key::F12,
+        _ => return None
+    };
+    Some(key)
+}
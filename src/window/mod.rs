@@ -5,10 +5,14 @@ use gl_pipelines::*;
 use gl_pipelines::window::{EventHandler, MouseButton, MouseWheelDirection, ParametrizedEventHandler, WindowContext};
 
 pub mod monitor_obj_loader;
+#[cfg(feature = "egui")]
+mod indexed_egui;
 use monitor_obj_loader::Vec4;
-use crate::rendering::blittable::{BufferProviderMut, Rect, SizedSurface};
+use crate::rendering::blittable::{BufferProvider, BufferProviderMut, Rect, SizedSurface};
 use crate::math_utils::Barycentric2D;
 use crate::window::monitor_obj_loader::Mesh;
+#[cfg(feature = "egui")]
+use crate::window::indexed_egui::IndexedEgui;
 
 const IMAGE_BYTES: &[u8] = include_bytes!("monitor_mask.png");
 
@@ -142,6 +146,37 @@ pub enum KeyMod {
     Command
 }
 
+#[derive(Copy, Clone, Hash, PartialEq, Eq)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftStick,
+    RightStick,
+    Start,
+    Select,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight
+}
+
+#[derive(Copy, Clone, Hash, PartialEq, Eq)]
+#[repr(usize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger
+}
+
+const GAMEPAD_AXIS_COUNT: usize = 6;
+
 #[derive(Copy, Clone, Debug)]
 pub struct KeyMods {
     pub shift: bool,
@@ -276,7 +311,11 @@ impl TryFrom<gl_pipelines::window::KeyCode> for KeyCode {
 }
 
 pub struct RetroBlitContext {
+    #[cfg(feature = "egui")]
     egui: gl_pipelines::egui_integration::EguiMq,
+    /// the context of the overlay or of the indexed egui, depending on the egui mode
+    #[cfg(feature = "egui")]
+    egui_ctx: egui::Context,
     buffer_width: usize,
     buffer_height: usize,
     colors: [u8; 256 * 3],
@@ -284,9 +323,30 @@ pub struct RetroBlitContext {
     mouse_x: f32,
     mouse_y: f32,
     keys_pressed: HashSet<KeyCode>,
+    keys_just_pressed: HashSet<KeyCode>,
+    keys_just_released: HashSet<KeyCode>,
     key_mods_pressed: KeyMods,
+    mouse_buttons_pressed: [bool; 3],
+    mouse_buttons_just_pressed: [bool; 3],
+    mouse_buttons_just_released: [bool; 3],
+    mouse_wheel_delta: (f32, f32),
+    text_input: String,
+    gamepad_buttons_pressed: HashSet<GamepadButton>,
+    gamepad_axes: [f32; GAMEPAD_AXIS_COUNT],
     quit_fired: bool,
-    cursor_hidden_fired: Option<bool>
+    cursor_hidden_fired: Option<bool>,
+    pointer_inside: bool,
+    pointer_delta: (f32, f32)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PointerState {
+    /// position in buffer pixels. When the pointer leaves the emulated screen,
+    /// the last position inside of it is kept
+    pub position: (f32, f32),
+    pub inside_screen: bool,
+    /// raw relative motion in window pixels accumulated since the previous update
+    pub delta: (f32, f32)
 }
 
 impl RetroBlitContext {
@@ -297,6 +357,18 @@ impl RetroBlitContext {
     pub fn show_cursor(&mut self) {
         self.cursor_hidden_fired = Some(false);
     }
+
+    pub fn is_pointer_inside_screen(&self) -> bool {
+        self.pointer_inside
+    }
+
+    pub fn get_pointer_state(&self) -> PointerState {
+        PointerState {
+            position: (self.mouse_x, self.mouse_y),
+            inside_screen: self.pointer_inside,
+            delta: self.pointer_delta
+        }
+    }
 }
 
 pub enum ScrollKind {
@@ -319,6 +391,10 @@ impl SizedSurface for RetroBlitContext {
     }
 }
 
+impl BufferProvider<u8> for RetroBlitContext {
+    fn get_buffer(&self) -> &[u8] { &self.buffer_pixels }
+}
+
 impl BufferProviderMut<u8> for RetroBlitContext  {
     fn get_buffer_mut(&mut self) -> &mut [u8] { &mut self.buffer_pixels }
 }
@@ -362,20 +438,24 @@ impl RetroBlitContext {
         }
     }
 
+    #[cfg(feature = "egui")]
     pub fn is_egui_wants_keyboard_input(&self) -> bool {
-        self.egui.egui_ctx().wants_keyboard_input()
+        self.egui_ctx.wants_keyboard_input()
     }
 
+    #[cfg(feature = "egui")]
     pub fn is_egui_wants_pointer_input(&self) -> bool {
-        self.egui.egui_ctx().wants_pointer_input()
+        self.egui_ctx.wants_pointer_input()
     }
 
+    #[cfg(feature = "egui")]
     pub fn is_egui_area_under_pointer(&self) -> bool {
-        self.egui.egui_ctx().is_pointer_over_area()
+        self.egui_ctx.is_pointer_over_area()
     }
 
+    #[cfg(feature = "egui")]
     pub fn get_egui_ctx(&self) -> egui::Context {
-        self.egui.egui_ctx().clone()
+        self.egui_ctx.clone()
     }
 
     pub fn is_key_mod_pressed(&self, key_mod: KeyMod) -> bool {
@@ -391,10 +471,88 @@ impl RetroBlitContext {
         self.keys_pressed.contains(&key_code)
     }
 
+    /// true only during the first update after the key went down. Key repeats are ignored
+    pub fn is_key_just_pressed(&self, key_code: KeyCode) -> bool {
+        self.keys_just_pressed.contains(&key_code)
+    }
+
+    /// true only during the first update after the key went up
+    pub fn is_key_just_released(&self, key_code: KeyCode) -> bool {
+        self.keys_just_released.contains(&key_code)
+    }
+
     pub fn get_mouse_pos(&self) -> (f32, f32) {
         (self.mouse_x, self.mouse_y)
     }
 
+    /// button numbers are the same as in ContextHandler::on_mouse_down:
+    /// 0 is left, 1 is middle and 2 is right
+    pub fn is_mouse_button_pressed(&self, button_number: u8) -> bool {
+        self.mouse_buttons_pressed
+            .get(button_number as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    pub fn is_mouse_button_just_pressed(&self, button_number: u8) -> bool {
+        self.mouse_buttons_just_pressed
+            .get(button_number as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    pub fn is_mouse_button_just_released(&self, button_number: u8) -> bool {
+        self.mouse_buttons_just_released
+            .get(button_number as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// wheel movement accumulated since the previous update.
+    /// Positive y means scrolling up (away from the user)
+    pub fn get_mouse_wheel_delta(&self) -> (f32, f32) {
+        self.mouse_wheel_delta
+    }
+
+    /// characters typed since the previous update. Control characters are filtered out,
+    /// so backspace, enter etc. should be handled with is_key_just_pressed
+    pub fn get_text_input(&self) -> &str {
+        &self.text_input
+    }
+
+    fn reset_frame_input_state(&mut self) {
+        self.keys_just_pressed.clear();
+        self.keys_just_released.clear();
+        self.mouse_buttons_just_pressed = [false; 3];
+        self.mouse_buttons_just_released = [false; 3];
+        self.mouse_wheel_delta = (0.0, 0.0);
+        self.pointer_delta = (0.0, 0.0);
+        self.text_input.clear();
+    }
+
+    pub fn is_gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons_pressed.contains(&button)
+    }
+
+    /// sticks are in a range of -1.0..=1.0, triggers are in a range of 0.0..=1.0
+    pub fn get_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes[axis as usize]
+    }
+
+    /// The window backend doesn't report game controllers,
+    /// so their state should be fed from outside (e.g. by polling gilrs at the start of update)
+    pub fn set_gamepad_button_pressed(&mut self, button: GamepadButton, pressed: bool) {
+        if pressed {
+            self.gamepad_buttons_pressed.insert(button);
+        } else {
+            self.gamepad_buttons_pressed.remove(&button);
+        }
+    }
+
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes[axis as usize] = value.clamp(-1.0, 1.0);
+    }
+
     pub fn get_palette(&self, index: u8) -> [u8; 3] {
         let offset = self.make_palette_offset(index as usize);
         [self.colors[offset], self.colors[offset + 1], self.colors[offset + 2]]
@@ -442,11 +600,27 @@ pub trait ContextHandler {
     fn on_mouse_up(&mut self, _ctx: &mut RetroBlitContext, _button_number: u8){}
     fn on_key_down(&mut self, _ctx: &mut RetroBlitContext, _key_code: KeyCode, _key_mods: KeyMods){}
     fn on_key_up(&mut self, _ctx: &mut RetroBlitContext, _key_code: KeyCode, _key_mods: KeyMods){}
+    fn on_char(&mut self, _ctx: &mut RetroBlitContext, _character: char){}
+    fn on_mouse_wheel(&mut self, _ctx: &mut RetroBlitContext, _dx: f32, _dy: f32){}
     fn init(&mut self, ctx: &mut RetroBlitContext);
     fn update(&mut self, ctx: &mut RetroBlitContext, dt: f32);
+    /// read once when the window is created
+    #[cfg(feature = "egui")]
+    fn get_egui_mode(&self) -> EguiMode { EguiMode::Overlay }
+    #[cfg(feature = "egui")]
     fn egui(&mut self, _ctx: &mut RetroBlitContext, _egui_ctx: egui::Context) {}
 }
 
+#[cfg(feature = "egui")]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum EguiMode {
+    /// egui is drawn in true color on top of the screen, in window pixels
+    Overlay,
+    /// egui is laid out in buffer pixels and drawn into the indexed buffer after update,
+    /// with colors quantized to the palette. It shows up in screenshots and gets the monitor effects
+    Indexed
+}
+
 fn get_buffer_dimensions(handler: &impl ContextHandler) -> (usize, usize) {
     handler.get_window_mode().get_buffer_dimensions()
 }
@@ -464,6 +638,8 @@ pub struct Stage<CtxHandler: ContextHandler> {
     offscreen_pass: RenderPass,
     context_data: RetroBlitContext,
     handler: CtxHandler,
+    #[cfg(feature = "egui")]
+    indexed_egui: Option<IndexedEgui>,
     buffer_texture: Texture,
     colors_texture: Texture,
     last_instant: Instant
@@ -630,8 +806,24 @@ impl<CtxHandler: ContextHandler> ParametrizedEventHandler<CtxHandler> for Stage<
 
         let (buffer_width, buffer_height) = get_buffer_dimensions(&handler);
 
+        #[cfg(feature = "egui")]
+        let indexed_egui = match handler.get_egui_mode() {
+            EguiMode::Overlay => None,
+            EguiMode::Indexed => Some(IndexedEgui::new())
+        };
+        #[cfg(feature = "egui")]
+        let egui = gl_pipelines::egui_integration::EguiMq::new(ctx);
+        #[cfg(feature = "egui")]
+        let egui_ctx = match &indexed_egui {
+            Some(indexed_egui) => indexed_egui.egui_ctx().clone(),
+            None => egui.egui_ctx().clone()
+        };
+
         let mut context_data = RetroBlitContext {
-            egui: gl_pipelines::egui_integration::EguiMq::new(ctx),
+            #[cfg(feature = "egui")]
+            egui,
+            #[cfg(feature = "egui")]
+            egui_ctx,
             buffer_width,
             buffer_height,
             buffer_pixels: vec![0u8; buffer_width * buffer_height],
@@ -639,14 +831,25 @@ impl<CtxHandler: ContextHandler> ParametrizedEventHandler<CtxHandler> for Stage<
             mouse_x: 0.0,
             mouse_y: 0.0,
             keys_pressed: HashSet::new(),
+            keys_just_pressed: HashSet::new(),
+            keys_just_released: HashSet::new(),
             key_mods_pressed: KeyMods {
                 shift: false,
                 control: false,
                 option: false,
                 command: false
             },
+            mouse_buttons_pressed: [false; 3],
+            mouse_buttons_just_pressed: [false; 3],
+            mouse_buttons_just_released: [false; 3],
+            mouse_wheel_delta: (0.0, 0.0),
+            text_input: String::new(),
+            gamepad_buttons_pressed: HashSet::new(),
+            gamepad_axes: [0.0; GAMEPAD_AXIS_COUNT],
             quit_fired: false,
-            cursor_hidden_fired: None
+            cursor_hidden_fired: None,
+            pointer_inside: false,
+            pointer_delta: (0.0, 0.0)
         };
 
         let mut handler = handler;
@@ -776,6 +979,8 @@ impl<CtxHandler: ContextHandler> ParametrizedEventHandler<CtxHandler> for Stage<
             offscreen_pass,
             context_data,
             handler,
+            #[cfg(feature = "egui")]
+            indexed_egui,
             buffer_texture: buffer_texture.clone(),
             colors_texture: colors_texture.clone(),
             last_instant: Instant::now()
@@ -800,15 +1005,26 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
         let dt = self.last_instant.elapsed().as_micros() as f32 / 1000000.0;
         self.last_instant = Instant::now();
         self.handler.update(&mut self.context_data, dt);
+        #[cfg(feature = "egui")]
+        if let Some(indexed_egui) = self.indexed_egui.as_mut() {
+            indexed_egui.begin_frame(&self.context_data);
+            let egui_ctx = indexed_egui.egui_ctx().clone();
+            self.handler.egui(&mut self.context_data, egui_ctx);
+            indexed_egui.end_frame(&mut self.context_data);
+        }
+        self.context_data.reset_frame_input_state();
         self.colors_texture.update(ctx, &self.context_data.colors);
         self.buffer_texture.update(ctx, &self.context_data.buffer_pixels);
     }
 
-    fn draw(&mut self, ctx: &mut Context, win_ctx: &mut WindowContext) {
-        self.context_data.egui.on_frame_start(ctx);
-        let egui_ctx = self.context_data.egui.egui_ctx().clone();
-        self.handler.egui(&mut self.context_data, egui_ctx);
-        self.context_data.egui.on_frame_end(win_ctx);
+    fn draw(&mut self, ctx: &mut Context, _win_ctx: &mut WindowContext) {
+        #[cfg(feature = "egui")]
+        if self.indexed_egui.is_none() {
+            self.context_data.egui.on_frame_start(ctx);
+            let egui_ctx = self.context_data.egui.egui_ctx().clone();
+            self.handler.egui(&mut self.context_data, egui_ctx);
+            self.context_data.egui.on_frame_end(_win_ctx);
+        }
 
         { // render out color buffer into offscreen texture
             ctx.begin_pass(
@@ -841,7 +1057,10 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
         }
         ctx.end_render_pass();
 
-        self.context_data.egui.draw(ctx);
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            egui.draw(ctx);
+        }
 
         ctx.commit_frame();
     }
@@ -850,9 +1069,11 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
         &mut self,
         ctx: &mut Context, _win_ctx: &mut WindowContext,
         x: i32, y: i32,
-        _x_rel: i32, _y_rel: i32
+        x_rel: i32, y_rel: i32
     ) {
         {
+            self.context_data.pointer_delta.0 += x_rel as f32;
+            self.context_data.pointer_delta.1 += y_rel as f32;
             let screen_size = ctx.get_window_size();
             let aspect = screen_size.0 as f32 / screen_size.1 as f32;
 
@@ -861,13 +1082,28 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
 
             self.check_for_hit_test(x, y);
         }
-        let dpi = ctx.get_dpi();
-        self.context_data.egui.mouse_motion_event(ctx, x as f32 * dpi.0, y as f32 * dpi.1);
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            let dpi = ctx.get_dpi();
+            egui.mouse_motion_event(ctx, x as f32 * dpi.0, y as f32 * dpi.1);
+        }
     }
 
-    fn mouse_wheel_event(&mut self, gfx_ctx: &mut Context, _win_ctx: &mut WindowContext, dx: i32, dy: i32, _direction: MouseWheelDirection) {
-        let dpi = gfx_ctx.get_dpi();
-        self.context_data.egui.mouse_wheel_event(gfx_ctx, dx as f32 * dpi.0, dy as f32 * dpi.1);
+    fn mouse_wheel_event(&mut self, _gfx_ctx: &mut Context, _win_ctx: &mut WindowContext, dx: i32, dy: i32, direction: MouseWheelDirection) {
+        {
+            let (wheel_dx, wheel_dy) = match direction {
+                MouseWheelDirection::Flipped => (-dx as f32, -dy as f32),
+                _ => (dx as f32, dy as f32)
+            };
+            self.context_data.mouse_wheel_delta.0 += wheel_dx;
+            self.context_data.mouse_wheel_delta.1 += wheel_dy;
+            self.handler.on_mouse_wheel(&mut self.context_data, wheel_dx, wheel_dy);
+        }
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            let dpi = _gfx_ctx.get_dpi();
+            egui.mouse_wheel_event(_gfx_ctx, dx as f32 * dpi.0, dy as f32 * dpi.1);
+        }
     }
 
     fn mouse_button_down_event(
@@ -879,14 +1115,29 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
         {
             self.mouse_motion_event(ctx, win_ctx, x as _, y as _, 0, 0);
             match button {
-                MouseButton::Left => { self.handler.on_mouse_down(&mut self.context_data, 0); },
-                MouseButton::Middle => { self.handler.on_mouse_down(&mut self.context_data, 1); },
-                MouseButton::Right => { self.handler.on_mouse_down(&mut self.context_data, 2); },
+                MouseButton::Left => {
+                    self.context_data.mouse_buttons_pressed[0] = true;
+                    self.context_data.mouse_buttons_just_pressed[0] = true;
+                    self.handler.on_mouse_down(&mut self.context_data, 0);
+                },
+                MouseButton::Middle => {
+                    self.context_data.mouse_buttons_pressed[1] = true;
+                    self.context_data.mouse_buttons_just_pressed[1] = true;
+                    self.handler.on_mouse_down(&mut self.context_data, 1);
+                },
+                MouseButton::Right => {
+                    self.context_data.mouse_buttons_pressed[2] = true;
+                    self.context_data.mouse_buttons_just_pressed[2] = true;
+                    self.handler.on_mouse_down(&mut self.context_data, 2);
+                },
                 _ => {}
             }
         }
-        let dpi = ctx.get_dpi();
-        self.context_data.egui.mouse_button_down_event(ctx, button, x as f32 * dpi.0, y as f32 * dpi.1);
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            let dpi = ctx.get_dpi();
+            egui.mouse_button_down_event(ctx, button, x as f32 * dpi.0, y as f32 * dpi.1);
+        }
     }
 
     fn mouse_button_up_event(
@@ -898,23 +1149,49 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
         {
             self.mouse_motion_event(ctx, win_ctx,x as _, y as _, 0, 0);
             match button {
-                MouseButton::Left => { self.handler.on_mouse_up(&mut self.context_data, 0); },
-                MouseButton::Middle => { self.handler.on_mouse_up(&mut self.context_data, 1); },
-                MouseButton::Right => { self.handler.on_mouse_up(&mut self.context_data, 2); },
+                MouseButton::Left => {
+                    self.context_data.mouse_buttons_pressed[0] = false;
+                    self.context_data.mouse_buttons_just_released[0] = true;
+                    self.handler.on_mouse_up(&mut self.context_data, 0);
+                },
+                MouseButton::Middle => {
+                    self.context_data.mouse_buttons_pressed[1] = false;
+                    self.context_data.mouse_buttons_just_released[1] = true;
+                    self.handler.on_mouse_up(&mut self.context_data, 1);
+                },
+                MouseButton::Right => {
+                    self.context_data.mouse_buttons_pressed[2] = false;
+                    self.context_data.mouse_buttons_just_released[2] = true;
+                    self.handler.on_mouse_up(&mut self.context_data, 2);
+                },
                 _ => {}
             }
         }
-        let dpi = ctx.get_dpi();
-        self.context_data.egui.mouse_button_up_event(ctx, button, x as f32 * dpi.0, y as f32 * dpi.1);
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            let dpi = ctx.get_dpi();
+            egui.mouse_button_up_event(ctx, button, x as f32 * dpi.0, y as f32 * dpi.1);
+        }
     }
 
     fn char_event(&mut self, _gfx_ctx: &mut Context, _win_ctx: &mut WindowContext, character: char) {
-        self.context_data.egui.char_event(character);
+        if !character.is_control() {
+            self.context_data.text_input.push(character);
+            self.handler.on_char(&mut self.context_data, character);
+            #[cfg(feature = "egui")]
+            if let Some(indexed_egui) = self.indexed_egui.as_mut() {
+                indexed_egui.char_event(character);
+            }
+        }
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            egui.char_event(character);
+        }
     }
 
     fn key_down_event(
         &mut self,
-        ctx: &mut Context, win_ctx: &mut WindowContext,
+        _ctx: &mut Context, _win_ctx: &mut WindowContext,
         keycode: gl_pipelines::window::KeyCode,
         keymods: gl_pipelines::window::KeyMods,
         _repeat: bool,
@@ -928,15 +1205,24 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
             };
             self.context_data.key_mods_pressed = new_key_mods;
             if let Ok(key_code) = KeyCode::try_from(keycode) {
-                self.context_data.keys_pressed.insert(key_code);
+                if self.context_data.keys_pressed.insert(key_code) {
+                    self.context_data.keys_just_pressed.insert(key_code);
+                }
                 self.handler.on_key_down(
                     &mut self.context_data,
                     key_code,
                     new_key_mods
                 );
+                #[cfg(feature = "egui")]
+                if let Some(indexed_egui) = self.indexed_egui.as_mut() {
+                    indexed_egui.key_event(key_code, new_key_mods, true);
+                }
             }
         }
-        self.context_data.egui.key_down_event(ctx, win_ctx, keycode, keymods);
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            egui.key_down_event(_ctx, _win_ctx, keycode, keymods);
+        }
     }
 
     fn key_up_event(
@@ -954,23 +1240,43 @@ impl<CtxHandler: ContextHandler> EventHandler for Stage<CtxHandler> {
             };
             self.context_data.key_mods_pressed = new_key_mods;
             if let Ok(key_code) = KeyCode::try_from(keycode) {
-                self.context_data.keys_pressed.remove(&key_code);
+                if self.context_data.keys_pressed.remove(&key_code) {
+                    self.context_data.keys_just_released.insert(key_code);
+                }
                 self.handler.on_key_up(
                     &mut self.context_data,
                     key_code,
                     new_key_mods
                 );
+                #[cfg(feature = "egui")]
+                if let Some(indexed_egui) = self.indexed_egui.as_mut() {
+                    indexed_egui.key_event(key_code, new_key_mods, false);
+                }
             }
         }
-        self.context_data.egui.key_up_event(keycode, keymods);
+        #[cfg(feature = "egui")]
+        if let Some(egui) = self.overlay_egui() {
+            egui.key_up_event(keycode, keymods);
+        }
     }
 }
 
 impl<CtxHandler: ContextHandler> Stage<CtxHandler> {
+    /// events go to the overlay only, the indexed egui reads the context input instead
+    #[cfg(feature = "egui")]
+    fn overlay_egui(&mut self) -> Option<&mut gl_pipelines::egui_integration::EguiMq> {
+        match self.indexed_egui {
+            Some(_) => None,
+            None => Some(&mut self.context_data.egui)
+        }
+    }
+
     fn check_for_hit_test(&mut self, x: f32, y: f32) {
         match self.handler.get_window_mode() {
             WindowMode::ModeX | WindowMode::Mode13 => {
                 let pt = Vec4 {x: x.clamp(-1.0, 1.0), y: y.clamp(-1.0, 1.0), z: 0.0, w: 1.0 };
+                let pt_clamped = pt.x != x || pt.y != y;
+                self.context_data.pointer_inside = false;
 
                 let mut offset = 0;
                 while offset < self.screen_mesh.indices.len() {
@@ -994,6 +1300,7 @@ impl<CtxHandler: ContextHandler> Stage<CtxHandler> {
                             let v = 1.0 - (bar_u * vert0.uv.y + bar_v * vert1.uv.y + bar_w * vert2.uv.y);
                             self.context_data.mouse_x = u * self.context_data.buffer_width as f32;
                             self.context_data.mouse_y = v * self.context_data.buffer_height as f32;
+                            self.context_data.pointer_inside = !pt_clamped;
                             return;
                         }
                     }
@@ -1002,8 +1309,13 @@ impl<CtxHandler: ContextHandler> Stage<CtxHandler> {
             },
             _ => {
                 let aspect = self.context_data.buffer_width as f32 / self.context_data.buffer_height as f32;
-                let u = ((x / aspect).clamp(-1.0, 1.0) + 1.0) / 2.0;
-                let v = 1.0 - (y.clamp(-1.0, 1.0) + 1.0) / 2.0;
+                self.context_data.pointer_inside = (-1.0..=1.0).contains(&(x / aspect)) &&
+                    (-1.0..=1.0).contains(&y);
+                if !self.context_data.pointer_inside {
+                    return;
+                }
+                let u = (x / aspect + 1.0) / 2.0;
+                let v = 1.0 - (y + 1.0) / 2.0;
                 self.context_data.mouse_x = u * self.context_data.buffer_width as f32;
                 self.context_data.mouse_y = v * self.context_data.buffer_height as f32;
             }